@@ -8,11 +8,9 @@ use bevy::{
     app::PluginGroupBuilder,
     prelude::*,
     time::FixedTimestep,
-    window::{close_on_esc, WindowId, WindowMode},
-};
-use bevy_vulkano::{
-    BevyVulkanoContext, BevyVulkanoWindows, VulkanoWinitConfig, VulkanoWinitPlugin,
+    window::{close_on_esc, PrimaryWindow, WindowId, WindowMode},
 };
+use bevy_vulkano::{BevyVulkanoContext, BevyVulkanoWindows, VulkanoWinitPlugin};
 use vulkano::image::ImageAccess;
 
 use crate::{game_of_life::GameOfLifeComputePipeline, place_over_frame::RenderPassPlaceOverFrame};
@@ -35,19 +33,16 @@ impl PluginGroup for PluginBundle {
 
 fn main() {
     App::new()
-        .insert_non_send_resource(VulkanoWinitConfig::default())
-        .add_plugins(PluginBundle.set(VulkanoWinitPlugin {
-            window_descriptor: WindowDescriptor {
-                width: 1024.0,
-                height: 1024.0,
-                title: "Bevy Vulkano Game Of Life".to_string(),
-                present_mode: bevy::window::PresentMode::Immediate,
-                resizable: true,
-                mode: WindowMode::Windowed,
-                position: WindowPosition::Centered,
-                ..WindowDescriptor::default()
-            },
-        }))
+        .add_plugins(PluginBundle.set(VulkanoWinitPlugin::new(WindowDescriptor {
+            width: 1024.0,
+            height: 1024.0,
+            title: "Bevy Vulkano Game Of Life".to_string(),
+            present_mode: bevy::window::PresentMode::Immediate,
+            resizable: true,
+            mode: WindowMode::Windowed,
+            position: WindowPosition::Centered,
+            ..WindowDescriptor::default()
+        })))
         .add_startup_system(create_pipelines)
         .add_system(close_on_esc)
         .add_system(draw_life_system)
@@ -129,23 +124,30 @@ fn game_of_life_pipeline_system(
     mut vulkano_windows: NonSendMut<BevyVulkanoWindows>,
     mut game_of_life: ResMut<GameOfLifeComputePipeline>,
     mut place_over_frame: ResMut<RenderPassPlaceOverFrame>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
 ) {
-    let primary_window = vulkano_windows.get_primary_window_renderer_mut().unwrap();
+    let Ok(primary_window) = primary_window.get_single() else {
+        return;
+    };
 
-    // Start frame
-    let before = match primary_window.acquire() {
-        Err(e) => {
+    // Start frame, skipped while the window is minimized or occluded
+    let before = match vulkano_windows.acquire(primary_window) {
+        None => return,
+        Some(Err(e)) => {
             bevy::log::error!("Failed to start frame: {}", e);
             return;
         }
-        Ok(f) => f,
+        Some(Ok(f)) => f,
     };
 
     let after_compute = game_of_life.compute(before, [1.0, 0.0, 0.0, 1.0], [0.0; 4]);
     let color_image = game_of_life.color_image();
-    let final_image = primary_window.swapchain_image_view();
+    let final_image = vulkano_windows
+        .get_window_renderer(primary_window)
+        .unwrap()
+        .swapchain_image_view();
     let after_render = place_over_frame.render(after_compute, color_image, final_image);
 
     // Finish Frame
-    primary_window.present(after_render, true);
+    vulkano_windows.present(primary_window, after_render, true);
 }
@@ -9,7 +9,7 @@ use bevy::{
 #[cfg(feature = "example_has_gui")]
 use bevy_vulkano::egui_winit_vulkano::egui;
 #[cfg(feature = "example_has_gui")]
-use bevy_vulkano::{BevyVulkanoWindows, VulkanoWinitConfig, VulkanoWinitPlugin};
+use bevy_vulkano::{primary_window_presentable, BevyVulkanoWindows, VulkanoWinitPlugin};
 
 #[cfg(feature = "example_has_gui")]
 pub struct PluginBundle;
@@ -38,32 +38,31 @@ fn main() {
 #[cfg(feature = "example_has_gui")]
 fn main() {
     App::new()
-        .insert_non_send_resource(VulkanoWinitConfig {
-            // Since we're only drawing gui, let's clear each frame
-            is_gui_overlay: true,
-            ..VulkanoWinitConfig::default()
-        })
-        .add_plugins(PluginBundle.set(VulkanoWinitPlugin {
-            window_descriptor: WindowDescriptor {
-                width: 1920.0,
-                height: 1080.0,
-                title: "Bevy Vulkano Primary Window".to_string(),
-                present_mode: bevy::window::PresentMode::Fifo,
-                resizable: true,
-                mode: WindowMode::Windowed,
-                ..WindowDescriptor::default()
-            },
-        }))
+        .add_plugins(
+            PluginBundle.set(
+                VulkanoWinitPlugin::new(WindowDescriptor {
+                    width: 1920.0,
+                    height: 1080.0,
+                    title: "Bevy Vulkano Primary Window".to_string(),
+                    present_mode: bevy::window::PresentMode::Fifo,
+                    resizable: true,
+                    mode: WindowMode::Windowed,
+                    ..WindowDescriptor::default()
+                })
+                // Since we're only drawing gui, let's clear each frame
+                .with_gui_overlay(true),
+            ),
+        )
         .add_system(close_on_esc)
         .add_startup_system(create_new_window_system)
         .add_system(create_new_window_on_space_system)
-        .add_system_set_to_stage(
-            // Add render system after PostUpdate
-            CoreStage::PostUpdate,
-            SystemSet::new()
-                .with_system(main_render_system_primary_window)
-                .with_system(main_render_system_secondary_window),
+        // Add render systems after PostUpdate, skipping windows that are minimized or occluded
+        .add_system(
+            main_render_system_primary_window
+                .run_if(primary_window_presentable)
+                .in_base_set(CoreSet::PostUpdate),
         )
+        .add_system(main_render_system_secondary_window.in_base_set(CoreSet::PostUpdate))
         .run();
 }
 
@@ -131,9 +130,18 @@ pub fn main_render_system_primary_window(mut vulkano_windows: NonSendMut<BevyVul
 #[cfg(feature = "example_has_gui")]
 pub fn main_render_system_secondary_window(mut vulkano_windows: NonSendMut<BevyVulkanoWindows>) {
     let primary_window_id = vulkano_windows.get_primary_winit_window().unwrap().id();
+    let presentable = vulkano_windows
+        .iter()
+        .map(|(window_id, _)| *window_id)
+        .filter(|window_id| {
+            vulkano_windows
+                .get_window_entity(*window_id)
+                .map_or(false, |entity| vulkano_windows.is_presentable(entity))
+        })
+        .collect::<Vec<_>>();
     for (window_id, (window_renderer, gui)) in vulkano_windows.iter_mut() {
-        // Skip primary window
-        if *window_id == primary_window_id {
+        // Skip primary window, and windows that are minimized or occluded
+        if *window_id == primary_window_id || !presentable.contains(window_id) {
             continue;
         }
         // Render on secondary window
@@ -1,5 +1,5 @@
 use bevy::{app::AppExit, prelude::*};
-use bevy_vulkano::{BevyVulkanoContext, VulkanoWinitConfig, VulkanoWinitPlugin};
+use bevy_vulkano::{BevyVulkanoContext, VulkanoContextPlugin};
 use vulkano::{
     buffer::{BufferUsage, CpuAccessibleBuffer},
     command_buffer::{
@@ -17,11 +17,8 @@ use vulkano::{
 
 fn main() {
     App::new()
-        .insert_non_send_resource(VulkanoWinitConfig {
-            add_primary_window: false,
-            ..default()
-        })
-        .add_plugin(VulkanoWinitPlugin::default())
+        // Only the vulkano context, no event loop or windows
+        .add_plugin(VulkanoContextPlugin::default())
         .add_startup_system(run_compute_shader_once_then_exit)
         .run();
 }
@@ -0,0 +1,413 @@
+use std::{error::Error, sync::Arc};
+
+use bevy::{log::warn, prelude::Resource};
+use vulkano::{
+    device::{
+        physical::{PhysicalDevice, PhysicalDeviceType},
+        Device, DeviceExtensions, Features,
+    },
+    instance::{Instance, InstanceCreateInfo, InstanceExtensions},
+    Version, VulkanLibrary,
+};
+use vulkano_util::context::{VulkanoConfig, VulkanoContext};
+
+/// Device extensions and features required or requested by plugins. Add to it before
+/// [`VulkanoContextPlugin`](crate::VulkanoContextPlugin) is added, which creates the device with
+/// them, e.g. with `app.world.get_resource_or_insert_with(DeviceRequirements::default)`. Changes
+/// made afterwards are ignored with a warning.
+///
+/// Only devices supporting the required extensions and features are selected. Optional ones are
+/// enabled if the selected device supports all of them. Each request is enabled or skipped as a
+/// whole, check [`EffectiveVulkanoConfig`] for whether they were. Requirements of the extensions and
+/// features themselves aren't resolved, so request a feature together with the extensions it
+/// depends on, using [`DeviceRequirements::request`].
+#[derive(Debug, Default, Clone, Resource)]
+pub struct DeviceRequirements {
+    required_extensions: DeviceExtensions,
+    required_features: Features,
+    optional: Vec<(DeviceExtensions, Features)>,
+}
+
+impl DeviceRequirements {
+    pub fn require_extensions(&mut self, extensions: DeviceExtensions) -> &mut Self {
+        self.required_extensions = self.required_extensions.union(&extensions);
+        self
+    }
+
+    pub fn request_extensions(&mut self, extensions: DeviceExtensions) -> &mut Self {
+        self.request(extensions, Features::empty())
+    }
+
+    pub fn require_features(&mut self, features: Features) -> &mut Self {
+        self.required_features = self.required_features.union(&features);
+        self
+    }
+
+    pub fn request_features(&mut self, features: Features) -> &mut Self {
+        self.request(DeviceExtensions::empty(), features)
+    }
+
+    /// Requests extensions and features that are only enabled together, e.g. a feature and the
+    /// extension providing it
+    pub fn request(&mut self, extensions: DeviceExtensions, features: Features) -> &mut Self {
+        self.optional.push((extensions, features));
+        self
+    }
+
+    /// Adds the requirements to the config. The device that would be selected is looked up with a
+    /// temporary instance to check which optional extensions and features it supports.
+    // `VulkanoConfig` requires `Arc` wrapped device filter functions
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub(crate) fn apply(&self, config: &mut VulkanoConfig) {
+        let required_extensions = self.required_extensions;
+        let required_features = self.required_features;
+        let device_filter_fn = config.device_filter_fn.clone();
+        config.device_filter_fn = Arc::new(move |p| {
+            p.supported_extensions().contains(&required_extensions)
+                && p.supported_features().contains(&required_features)
+                && device_filter_fn(p)
+        });
+        config.device_extensions = config.device_extensions.union(&required_extensions);
+        config.device_features = config.device_features.union(&required_features);
+
+        if self.optional.is_empty() {
+            return;
+        }
+        let instance = probe_instance(&config.instance_create_info);
+        let physical_device = match select_physical_device(&instance, config) {
+            Some(physical_device) => physical_device,
+            None => return,
+        };
+        for (extensions, features) in &self.optional {
+            if physical_device.supported_extensions().contains(extensions)
+                && physical_device.supported_features().contains(features)
+            {
+                config.device_extensions = config.device_extensions.union(extensions);
+                config.device_features = config.device_features.union(features);
+            } else {
+                warn!(
+                    "Skipping optional device extensions {:?} and features {:?}, not supported by \
+                     {}",
+                    extensions,
+                    features,
+                    physical_device.properties().device_name
+                );
+            }
+        }
+    }
+
+    /// Required extensions and features the device doesn't have enabled, for contexts created
+    /// outside the plugin. `None` if it satisfies the requirements.
+    pub(crate) fn missing_from(&self, device: &Device) -> Option<(DeviceExtensions, Features)> {
+        let extensions = self
+            .required_extensions
+            .difference(device.enabled_extensions());
+        let features = self.required_features.difference(device.enabled_features());
+        if extensions == DeviceExtensions::empty() && features == Features::empty() {
+            None
+        } else {
+            Some((extensions, features))
+        }
+    }
+}
+
+/// Environment variable overriding [`DeviceSelection`]: `discrete`, `integrated`, `virtual` or
+/// `cpu` prefer a device type, any other value a device whose name contains it. A `!` suffix, e.g.
+/// `cpu!`, requires a matching device instead. Empty values are ignored.
+pub const DEVICE_SELECTION_ENV_VAR: &str = "BEVY_VULKANO_DEVICE";
+
+/// How the physical device is selected, see
+/// [`VulkanoContextPlugin::with_device_selection`](crate::VulkanoContextPlugin::with_device_selection).
+/// Preferred devices are selected if available, otherwise the config's device priority is used,
+/// which prefers discrete, integrated, virtual and CPU devices in that order by default. Required
+/// devices are selected deterministically, e.g. lavapipe on CI, and creating the context panics
+/// if none matches. Can be overridden with the [`DEVICE_SELECTION_ENV_VAR`] environment variable.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum DeviceSelection {
+    /// Use the config's device priority
+    #[default]
+    Default,
+    /// Prefer devices of the type, e.g. [`PhysicalDeviceType::Cpu`] for a software renderer such
+    /// as lavapipe
+    PreferType(PhysicalDeviceType),
+    /// Prefer devices whose name contains the string, ignoring case
+    PreferName(String),
+    /// Only select devices of the type
+    RequireType(PhysicalDeviceType),
+    /// Only select devices whose name contains the string, ignoring case
+    RequireName(String),
+}
+
+impl DeviceSelection {
+    /// Selection from [`DEVICE_SELECTION_ENV_VAR`], if set and not empty
+    pub fn from_env() -> Option<DeviceSelection> {
+        DeviceSelection::parse(&std::env::var(DEVICE_SELECTION_ENV_VAR).ok()?)
+    }
+
+    /// Parses a [`DEVICE_SELECTION_ENV_VAR`] value
+    pub(crate) fn parse(value: &str) -> Option<DeviceSelection> {
+        let value = value.trim();
+        let (value, required) = match value.strip_suffix('!') {
+            Some(value) => (value.trim(), true),
+            None => (value, false),
+        };
+        if value.is_empty() {
+            return None;
+        }
+        let device_type = match value.to_lowercase().as_str() {
+            "discrete" => Some(PhysicalDeviceType::DiscreteGpu),
+            "integrated" => Some(PhysicalDeviceType::IntegratedGpu),
+            "virtual" => Some(PhysicalDeviceType::VirtualGpu),
+            "cpu" => Some(PhysicalDeviceType::Cpu),
+            _ => None,
+        };
+        Some(match (device_type, required) {
+            (Some(device_type), false) => DeviceSelection::PreferType(device_type),
+            (Some(device_type), true) => DeviceSelection::RequireType(device_type),
+            (None, false) => DeviceSelection::PreferName(value.to_string()),
+            (None, true) => DeviceSelection::RequireName(value.to_string()),
+        })
+    }
+
+    /// Whether only matching devices may be selected
+    pub fn is_required(&self) -> bool {
+        matches!(
+            self,
+            DeviceSelection::RequireType(_) | DeviceSelection::RequireName(_)
+        )
+    }
+
+    /// Whether the device matches the selection, always true for [`DeviceSelection::Default`]
+    pub fn matches(&self, physical_device: &PhysicalDevice) -> bool {
+        let properties = physical_device.properties();
+        match self {
+            DeviceSelection::Default => true,
+            DeviceSelection::PreferType(device_type)
+            | DeviceSelection::RequireType(device_type) => properties.device_type == *device_type,
+            DeviceSelection::PreferName(name) | DeviceSelection::RequireName(name) => properties
+                .device_name
+                .to_lowercase()
+                .contains(&name.to_lowercase()),
+        }
+    }
+
+    /// Filters out non matching devices if required, otherwise ranks matching devices first in the
+    /// config's device priority
+    // `VulkanoConfig` requires `Arc` wrapped device filter and priority functions
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub(crate) fn apply(&self, config: &mut VulkanoConfig) {
+        if *self == DeviceSelection::Default {
+            return;
+        }
+        let selection = self.clone();
+        if self.is_required() {
+            let device_filter_fn = config.device_filter_fn.clone();
+            config.device_filter_fn =
+                Arc::new(move |p| selection.matches(p) && device_filter_fn(p));
+        } else {
+            let device_priority_fn = config.device_priority_fn.clone();
+            config.device_priority_fn = Arc::new(move |p| {
+                let priority = device_priority_fn(p);
+                if selection.matches(p) {
+                    priority
+                } else {
+                    priority.saturating_add(u32::MAX / 2)
+                }
+            });
+        }
+    }
+}
+
+/// A physical device available to the default instance, see [`available_physical_devices`]
+#[derive(Debug, Clone)]
+pub struct PhysicalDeviceInfo {
+    pub name: String,
+    pub device_type: PhysicalDeviceType,
+    pub api_version: Version,
+    pub driver_version: u32,
+    pub driver_name: Option<String>,
+    pub driver_info: Option<String>,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    pub supported_extensions: DeviceExtensions,
+    pub supported_features: Features,
+}
+
+/// Lists the physical devices available with a default instance, e.g. to choose a
+/// [`DeviceSelection`]. Creates a temporary instance. Fails if Vulkan isn't available or the
+/// devices can't be enumerated.
+pub fn available_physical_devices() -> Result<Vec<PhysicalDeviceInfo>, Box<dyn Error>> {
+    let instance = try_probe_instance(&VulkanoConfig::default().instance_create_info)?;
+    Ok(instance
+        .enumerate_physical_devices()?
+        .map(|physical_device| {
+            let properties = physical_device.properties();
+            PhysicalDeviceInfo {
+                name: properties.device_name.clone(),
+                device_type: properties.device_type,
+                api_version: physical_device.api_version(),
+                driver_version: properties.driver_version,
+                driver_name: properties.driver_name.clone(),
+                driver_info: properties.driver_info.clone(),
+                vendor_id: properties.vendor_id,
+                device_id: properties.device_id,
+                supported_extensions: *physical_device.supported_extensions(),
+                supported_features: *physical_device.supported_features(),
+            }
+        })
+        .collect())
+}
+
+/// Creates a temporary instance like the one the context would be created with, to inspect
+/// physical devices before creating the context
+pub(crate) fn probe_instance(instance_create_info: &InstanceCreateInfo) -> Arc<Instance> {
+    try_probe_instance(instance_create_info).expect("Failed to create instance")
+}
+
+fn try_probe_instance(
+    instance_create_info: &InstanceCreateInfo,
+) -> Result<Arc<Instance>, Box<dyn Error>> {
+    let library = VulkanLibrary::new()?;
+    let enabled_extensions =
+        vulkano_win::required_extensions(&library).union(&instance_create_info.enabled_extensions);
+    Ok(Instance::new(library, InstanceCreateInfo {
+        enabled_extensions,
+        enumerate_portability: instance_create_info.enumerate_portability,
+        max_api_version: instance_create_info.max_api_version,
+        ..Default::default()
+    })?)
+}
+
+/// The physical device [`VulkanoContext::new`] selects with the config
+pub(crate) fn select_physical_device(
+    instance: &Arc<Instance>,
+    config: &VulkanoConfig,
+) -> Option<Arc<PhysicalDevice>> {
+    instance
+        .enumerate_physical_devices()
+        .expect("Failed to enumerate physical devices")
+        .filter(|p| (config.device_filter_fn)(p))
+        .min_by_key(|p| (config.device_priority_fn)(p))
+}
+
+/// Read-only copy of the instance and device configuration the [`VulkanoContext`] was created
+/// with, inserted by [`VulkanoContextPlugin`](crate::VulkanoContextPlugin). Contains what was
+/// actually enabled, including extensions added by vulkano, e.g. for surface creation.
+#[derive(Debug, Clone, Resource)]
+pub struct EffectiveVulkanoConfig {
+    instance_api_version: Version,
+    instance_extensions: InstanceExtensions,
+    instance_layers: Vec<String>,
+    device_name: String,
+    device_type: PhysicalDeviceType,
+    device_api_version: Version,
+    device_extensions: DeviceExtensions,
+    device_features: Features,
+}
+
+impl EffectiveVulkanoConfig {
+    pub(crate) fn from_context(context: &VulkanoContext) -> EffectiveVulkanoConfig {
+        let instance = context.instance();
+        let device = context.device();
+        let properties = device.physical_device().properties();
+        EffectiveVulkanoConfig {
+            instance_api_version: instance.api_version(),
+            instance_extensions: *instance.enabled_extensions(),
+            instance_layers: instance.enabled_layers().to_vec(),
+            device_name: properties.device_name.clone(),
+            device_type: properties.device_type,
+            device_api_version: device.api_version(),
+            device_extensions: *device.enabled_extensions(),
+            device_features: *device.enabled_features(),
+        }
+    }
+
+    pub fn instance_api_version(&self) -> Version {
+        self.instance_api_version
+    }
+
+    pub fn instance_extensions(&self) -> &InstanceExtensions {
+        &self.instance_extensions
+    }
+
+    pub fn instance_layers(&self) -> &[String] {
+        &self.instance_layers
+    }
+
+    pub fn device_name(&self) -> &str {
+        &self.device_name
+    }
+
+    pub fn device_type(&self) -> PhysicalDeviceType {
+        self.device_type
+    }
+
+    pub fn device_api_version(&self) -> Version {
+        self.device_api_version
+    }
+
+    pub fn device_extensions(&self) -> &DeviceExtensions {
+        &self.device_extensions
+    }
+
+    pub fn device_features(&self) -> &Features {
+        &self.device_features
+    }
+
+    /// Whether all of the extensions were enabled, e.g. ones requested through
+    /// [`DeviceRequirements`]
+    pub fn extensions_enabled(&self, extensions: &DeviceExtensions) -> bool {
+        self.device_extensions.contains(extensions)
+    }
+
+    /// Whether all of the features were enabled, e.g. ones requested through
+    /// [`DeviceRequirements`]
+    pub fn features_enabled(&self, features: &Features) -> bool {
+        self.device_features.contains(features)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_device_type() {
+        assert_eq!(
+            DeviceSelection::parse("discrete"),
+            Some(DeviceSelection::PreferType(PhysicalDeviceType::DiscreteGpu))
+        );
+        assert_eq!(
+            DeviceSelection::parse(" CPU "),
+            Some(DeviceSelection::PreferType(PhysicalDeviceType::Cpu))
+        );
+    }
+
+    #[test]
+    fn parse_device_name() {
+        assert_eq!(
+            DeviceSelection::parse("llvmpipe"),
+            Some(DeviceSelection::PreferName("llvmpipe".to_string()))
+        );
+    }
+
+    #[test]
+    fn parse_required() {
+        assert_eq!(
+            DeviceSelection::parse("cpu!"),
+            Some(DeviceSelection::RequireType(PhysicalDeviceType::Cpu))
+        );
+        assert_eq!(
+            DeviceSelection::parse("GeForce RTX !"),
+            Some(DeviceSelection::RequireName("GeForce RTX".to_string()))
+        );
+    }
+
+    #[test]
+    fn parse_empty() {
+        assert_eq!(DeviceSelection::parse(""), None);
+        assert_eq!(DeviceSelection::parse("  "), None);
+        assert_eq!(DeviceSelection::parse("!"), None);
+        assert_eq!(DeviceSelection::parse(" ! "), None);
+    }
+}
@@ -6,7 +6,13 @@ use bevy::{
         ButtonState,
     },
     math::Vec2,
-    window::CursorIcon,
+    prelude::Entity,
+    window::{CursorIcon, WindowLevel},
+};
+
+use crate::{
+    AxisMotion, KeyboardModifiers, SmartMagnify, TouchpadMagnify, TouchpadPressure, TouchpadRotate,
+    UserAttention, WindowTheme,
 };
 
 pub fn convert_keyboard_input(keyboard_input: &winit::event::KeyboardInput) -> KeyboardInput {
@@ -24,6 +30,15 @@ pub fn convert_element_state(element_state: winit::event::ElementState) -> Butto
     }
 }
 
+pub fn convert_modifiers_state(modifiers: winit::event::ModifiersState) -> KeyboardModifiers {
+    KeyboardModifiers {
+        shift: modifiers.shift(),
+        control: modifiers.ctrl(),
+        alt: modifiers.alt(),
+        logo: modifiers.logo(),
+    }
+}
+
 pub fn convert_mouse_button(mouse_button: winit::event::MouseButton) -> MouseButton {
     match mouse_button {
         winit::event::MouseButton::Left => MouseButton::Left,
@@ -38,12 +53,7 @@ pub fn convert_touch_input(
     location: winit::dpi::LogicalPosition<f32>,
 ) -> TouchInput {
     TouchInput {
-        phase: match touch_input.phase {
-            winit::event::TouchPhase::Started => TouchPhase::Started,
-            winit::event::TouchPhase::Moved => TouchPhase::Moved,
-            winit::event::TouchPhase::Ended => TouchPhase::Ended,
-            winit::event::TouchPhase::Cancelled => TouchPhase::Cancelled,
-        },
+        phase: convert_touch_phase(touch_input.phase),
         position: Vec2::new(location.x, location.y),
         force: touch_input.force.map(|f| match f {
             winit::event::Force::Calibrated {
@@ -61,6 +71,83 @@ pub fn convert_touch_input(
     }
 }
 
+pub fn convert_touch_phase(phase: winit::event::TouchPhase) -> TouchPhase {
+    match phase {
+        winit::event::TouchPhase::Started => TouchPhase::Started,
+        winit::event::TouchPhase::Moved => TouchPhase::Moved,
+        winit::event::TouchPhase::Ended => TouchPhase::Ended,
+        winit::event::TouchPhase::Cancelled => TouchPhase::Cancelled,
+    }
+}
+
+pub fn convert_touchpad_magnify(
+    window: Entity,
+    delta: f64,
+    phase: winit::event::TouchPhase,
+) -> TouchpadMagnify {
+    TouchpadMagnify {
+        window,
+        delta: delta as f32,
+        phase: convert_touch_phase(phase),
+    }
+}
+
+pub fn convert_touchpad_rotate(
+    window: Entity,
+    delta: f32,
+    phase: winit::event::TouchPhase,
+) -> TouchpadRotate {
+    TouchpadRotate {
+        window,
+        delta,
+        phase: convert_touch_phase(phase),
+    }
+}
+
+pub fn convert_smart_magnify(window: Entity) -> SmartMagnify {
+    SmartMagnify {
+        window,
+    }
+}
+
+pub fn convert_touchpad_pressure(window: Entity, pressure: f32, stage: i64) -> TouchpadPressure {
+    TouchpadPressure {
+        window,
+        pressure,
+        stage,
+    }
+}
+
+pub fn convert_axis_motion(window: Entity, axis: winit::event::AxisId, value: f64) -> AxisMotion {
+    AxisMotion {
+        window,
+        axis,
+        value,
+    }
+}
+
+pub fn convert_theme(theme: winit::window::Theme) -> WindowTheme {
+    match theme {
+        winit::window::Theme::Light => WindowTheme::Light,
+        winit::window::Theme::Dark => WindowTheme::Dark,
+    }
+}
+
+pub fn convert_window_level(window_level: WindowLevel) -> winit::window::WindowLevel {
+    match window_level {
+        WindowLevel::AlwaysOnBottom => winit::window::WindowLevel::AlwaysOnBottom,
+        WindowLevel::Normal => winit::window::WindowLevel::Normal,
+        WindowLevel::AlwaysOnTop => winit::window::WindowLevel::AlwaysOnTop,
+    }
+}
+
+pub fn convert_user_attention(attention: UserAttention) -> winit::window::UserAttentionType {
+    match attention {
+        UserAttention::Critical => winit::window::UserAttentionType::Critical,
+        UserAttention::Informational => winit::window::UserAttentionType::Informational,
+    }
+}
+
 pub fn convert_virtual_key_code(virtual_key_code: winit::event::VirtualKeyCode) -> KeyCode {
     match virtual_key_code {
         winit::event::VirtualKeyCode::Key1 => KeyCode::Key1,
@@ -0,0 +1,266 @@
+use bevy::{
+    input::{
+        keyboard::KeyboardInput,
+        mouse::{MouseButton, MouseButtonInput},
+        touch::{TouchInput, TouchPhase},
+        ButtonState,
+    },
+    prelude::{Component, Entity},
+    utils::{HashMap, HashSet},
+};
+
+/// A [`KeyboardInput`] event tagged with the window that received it. Sent alongside the global
+/// [`KeyboardInput`] event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowKeyboardInput {
+    pub window: Entity,
+    pub input: KeyboardInput,
+}
+
+/// A [`MouseButtonInput`] event tagged with the window that received it. Sent alongside the
+/// global [`MouseButtonInput`] event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowMouseButtonInput {
+    pub window: Entity,
+    pub input: MouseButtonInput,
+}
+
+/// A [`TouchInput`] event tagged with the window that was touched. Sent alongside the global
+/// [`TouchInput`] event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowTouchInput {
+    pub window: Entity,
+    pub input: TouchInput,
+}
+
+/// Two-finger pinch gesture on a touchpad. Positive delta values indicate magnification (zooming
+/// in) and negative values shrinking (zooming out).
+///
+/// Only sent on macOS.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TouchpadMagnify {
+    pub window: Entity,
+    pub delta: f32,
+    pub phase: TouchPhase,
+}
+
+/// Two-finger rotation gesture on a touchpad. Positive delta values indicate counterclockwise
+/// rotation and negative values clockwise rotation.
+///
+/// Only sent on macOS.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TouchpadRotate {
+    pub window: Entity,
+    pub delta: f32,
+    pub phase: TouchPhase,
+}
+
+/// Smart magnification gesture, e.g. a two-finger double tap on a Mac touchpad. Commonly used to
+/// toggle zoom on the object under the cursor.
+///
+/// Only sent on macOS 10.8 and later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SmartMagnify {
+    pub window: Entity,
+}
+
+/// Touchpad pressure, currently only reported by force touch capable Apple touchpads.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TouchpadPressure {
+    pub window: Entity,
+    /// Pressure level between 0 and 1
+    pub pressure: f32,
+    /// Click level
+    pub stage: i64,
+}
+
+/// Motion on an analog axis of a device while the window is focused. May report data redundant to
+/// other, more specific events.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AxisMotion {
+    pub window: Entity,
+    pub axis: u32,
+    pub value: f64,
+}
+
+/// State of the keyboard modifier keys, tracked per window entity from winit's `ModifiersChanged`
+/// events. Cleared when the window loses focus.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Component)]
+pub struct KeyboardModifiers {
+    pub shift: bool,
+    pub control: bool,
+    pub alt: bool,
+    pub logo: bool,
+}
+
+impl KeyboardModifiers {
+    /// Whether any modifier key is held
+    pub fn any(&self) -> bool {
+        self.shift || self.control || self.alt || self.logo
+    }
+}
+
+/// Keys and mouse buttons currently held down in a window. Used to send release events when the
+/// window loses focus, so `Input<KeyCode>` and `Input<MouseButton>` don't end up with stuck keys.
+#[derive(Default)]
+pub(crate) struct HeldInputs {
+    keys: HashMap<u32, KeyboardInput>,
+    buttons: HashSet<MouseButton>,
+}
+
+impl HeldInputs {
+    pub fn track_key(&mut self, input: &KeyboardInput) {
+        match input.state {
+            ButtonState::Pressed => {
+                self.keys.insert(input.scan_code, *input);
+            }
+            ButtonState::Released => {
+                self.keys.remove(&input.scan_code);
+            }
+        }
+    }
+
+    pub fn track_button(&mut self, button: MouseButton, state: ButtonState) {
+        match state {
+            ButtonState::Pressed => {
+                self.buttons.insert(button);
+            }
+            ButtonState::Released => {
+                self.buttons.remove(&button);
+            }
+        }
+    }
+
+    /// Drains held keys as release events
+    pub fn release_keys(&mut self) -> impl Iterator<Item = KeyboardInput> + '_ {
+        self.keys.drain().map(|(_, input)| KeyboardInput {
+            state: ButtonState::Released,
+            ..input
+        })
+    }
+
+    /// Drains held mouse buttons
+    pub fn release_buttons(&mut self) -> impl Iterator<Item = MouseButton> + '_ {
+        self.buttons.drain()
+    }
+}
+
+/// Tracks the finger that drives the cursor and left mouse button when touch to mouse emulation
+/// is enabled with [`VulkanoWinitConfig::emulate_mouse_from_touch`](crate::VulkanoWinitConfig).
+#[derive(Default)]
+pub(crate) struct TouchMouseEmulation {
+    touch_id: Option<u64>,
+}
+
+impl TouchMouseEmulation {
+    /// Returns whether the touch moves the cursor, and the left mouse button state it causes.
+    /// Only the first finger down drives the mouse until it is lifted.
+    pub fn update(&mut self, touch: &TouchInput) -> (bool, Option<ButtonState>) {
+        match touch.phase {
+            TouchPhase::Started if self.touch_id.is_none() => {
+                self.touch_id = Some(touch.id);
+                (true, Some(ButtonState::Pressed))
+            }
+            TouchPhase::Moved if self.touch_id == Some(touch.id) => (true, None),
+            TouchPhase::Ended | TouchPhase::Cancelled if self.touch_id == Some(touch.id) => {
+                self.touch_id = None;
+                (true, Some(ButtonState::Released))
+            }
+            _ => (false, None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{input::keyboard::KeyCode, math::Vec2};
+
+    use super::*;
+
+    fn key(scan_code: u32, key_code: KeyCode, state: ButtonState) -> KeyboardInput {
+        KeyboardInput {
+            scan_code,
+            key_code: Some(key_code),
+            state,
+        }
+    }
+
+    fn touch(id: u64, phase: TouchPhase) -> TouchInput {
+        TouchInput {
+            phase,
+            position: Vec2::ZERO,
+            force: None,
+            id,
+        }
+    }
+
+    #[test]
+    fn held_keys_are_released() {
+        let mut held_inputs = HeldInputs::default();
+        held_inputs.track_key(&key(42, KeyCode::LShift, ButtonState::Pressed));
+        held_inputs.track_key(&key(30, KeyCode::A, ButtonState::Pressed));
+        held_inputs.track_key(&key(30, KeyCode::A, ButtonState::Released));
+
+        let released = held_inputs.release_keys().collect::<Vec<_>>();
+        assert_eq!(released, vec![key(
+            42,
+            KeyCode::LShift,
+            ButtonState::Released
+        )]);
+        assert_eq!(held_inputs.release_keys().count(), 0);
+    }
+
+    #[test]
+    fn held_buttons_are_released() {
+        let mut held_inputs = HeldInputs::default();
+        held_inputs.track_button(MouseButton::Left, ButtonState::Pressed);
+        held_inputs.track_button(MouseButton::Right, ButtonState::Pressed);
+        held_inputs.track_button(MouseButton::Left, ButtonState::Released);
+
+        let released = held_inputs.release_buttons().collect::<Vec<_>>();
+        assert_eq!(released, vec![MouseButton::Right]);
+        assert_eq!(held_inputs.release_buttons().count(), 0);
+    }
+
+    #[test]
+    fn first_touch_drives_the_mouse() {
+        let mut emulation = TouchMouseEmulation::default();
+        assert_eq!(
+            emulation.update(&touch(1, TouchPhase::Started)),
+            (true, Some(ButtonState::Pressed))
+        );
+        // A second finger is ignored while the first is down
+        assert_eq!(
+            emulation.update(&touch(2, TouchPhase::Started)),
+            (false, None)
+        );
+        assert_eq!(
+            emulation.update(&touch(2, TouchPhase::Moved)),
+            (false, None)
+        );
+        assert_eq!(emulation.update(&touch(1, TouchPhase::Moved)), (true, None));
+        assert_eq!(
+            emulation.update(&touch(2, TouchPhase::Ended)),
+            (false, None)
+        );
+        assert_eq!(
+            emulation.update(&touch(1, TouchPhase::Ended)),
+            (true, Some(ButtonState::Released))
+        );
+    }
+
+    #[test]
+    fn cancelled_touch_releases_the_mouse() {
+        let mut emulation = TouchMouseEmulation::default();
+        emulation.update(&touch(1, TouchPhase::Started));
+        assert_eq!(
+            emulation.update(&touch(1, TouchPhase::Cancelled)),
+            (true, Some(ButtonState::Released))
+        );
+        // The next finger down takes over
+        assert_eq!(
+            emulation.update(&touch(3, TouchPhase::Started)),
+            (true, Some(ButtonState::Pressed))
+        );
+    }
+}
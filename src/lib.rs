@@ -11,42 +11,75 @@
 Pretty much the same as bevy_winit, but organized to use vulkano renderer backend.
 This allows you to create your own pipelines for rendering.
  */
+mod context;
 mod converters;
+mod input;
 mod pipeline_sync_data;
+mod surface;
+mod swapchain;
+mod validation;
 mod vulkano_windows;
+mod window;
+mod window_geometry;
+
+use std::{
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 use bevy::{
     app::{App, AppExit, Plugin},
     ecs::{
-        event::{Events, ManualEventReader},
-        system::SystemState,
+        event::{Event as BevyEvent, Events, ManualEventReader},
+        system::{SystemParam, SystemState},
     },
     input::{
         keyboard::KeyboardInput,
         mouse::{MouseButtonInput, MouseMotion, MouseScrollUnit, MouseWheel},
         touch::TouchInput,
+        ButtonState,
     },
     math::{ivec2, Vec2},
     prelude::*,
-    utils::HashSet,
+    utils::{HashMap, HashSet, Instant},
     window::{
-        CursorEntered, CursorLeft, CursorMoved, ExitCondition, FileDragAndDrop, PrimaryWindow,
+        CursorEntered, CursorLeft, CursorMoved, ExitCondition, FileDragAndDrop, Ime, PrimaryWindow,
         ReceivedCharacter, WindowBackendScaleFactorChanged, WindowCloseRequested, WindowClosed,
         WindowCreated, WindowFocused, WindowMoved, WindowResized, WindowScaleFactorChanged,
     },
 };
+pub use context::{
+    available_physical_devices, DeviceRequirements, DeviceSelection, EffectiveVulkanoConfig,
+    PhysicalDeviceInfo, DEVICE_SELECTION_ENV_VAR,
+};
+use context::{probe_instance, select_physical_device};
 #[cfg(feature = "gui")]
 pub use egui_winit_vulkano;
+pub use input::{
+    AxisMotion, KeyboardModifiers, SmartMagnify, TouchpadMagnify, TouchpadPressure, TouchpadRotate,
+    WindowKeyboardInput, WindowMouseButtonInput, WindowTouchInput,
+};
+use input::{HeldInputs, TouchMouseEmulation};
 pub use pipeline_sync_data::*;
+use surface::negotiate_present_mode;
+pub use surface::*;
+pub use swapchain::{SwapchainConfig, SwapchainInfo};
+use validation::panic_on_validation_error_system;
+pub use validation::ValidationLogging;
 use vulkano_util::context::{VulkanoConfig, VulkanoContext};
 pub use vulkano_windows::*;
+pub use window::*;
+pub use window_geometry::{PersistedWindowGeometry, WindowGeometry, WindowGeometryPlugin};
 use winit::{
     event::{self, DeviceEvent, Event, WindowEvent},
-    event_loop::{ControlFlow, EventLoop, EventLoopWindowTarget},
+    event_loop::{ControlFlow, EventLoop, EventLoopBuilder, EventLoopWindowTarget},
     window::WindowId,
 };
 
-/// Vulkano & winit related configurations
+/// Vulkano & winit related configurations. Inserted as a non-send resource by
+/// [`VulkanoWindowPlugin`] from its settings, configure the plugins instead of inserting it.
+/// Inserting it before the plugins is deprecated, but still takes precedence over their settings.
 pub struct VulkanoWinitConfig {
     /// Configures the winit library to return control to the main thread after
     /// the [run](bevy_app::App::run) loop is exited. Winit strongly recommends
@@ -59,7 +92,9 @@ pub struct VulkanoWinitConfig {
     /// `openbsd`. If set to true on an unsupported platform
     /// [run](bevy_app::App::run) will panic.
     pub return_from_run: bool,
-    /// Vulkano backend related configs
+    /// Vulkano backend related configs, only used if the config is inserted before
+    /// [`VulkanoContextPlugin`]. See [`VulkanoContextPlugin::with_vulkano_config`] and
+    /// [`EffectiveVulkanoConfig`] for the configuration in use.
     pub vulkano_config: VulkanoConfig,
     /// Whether the image gets cleared each frame by gui integration. This is only relevant if
     /// `gui` feature is set.
@@ -68,6 +103,22 @@ pub struct VulkanoWinitConfig {
     pub is_gui_overlay: bool,
     /// Control whether you want to run the app with or without a window
     pub add_primary_window: bool, // TODO: is this needed?
+    /// When to recreate swapchains after a window has been resized. Default is
+    /// [`ResizePolicy::Immediate`].
+    pub resize_policy: ResizePolicy,
+    /// Emulate mouse input from touch. The first finger down moves the cursor and holds the left
+    /// mouse button until it is lifted, so mouse driven UI works on touchscreens.
+    pub emulate_mouse_from_touch: bool,
+    /// Whether the primary window is shown when it's created. The primary window is created when
+    /// [`VulkanoWindowPlugin`] is added, so [`WindowVisible`] can't be inserted on it beforehand.
+    /// Default is true.
+    pub primary_window_visible: bool,
+    /// Swapchain settings of the primary window. Like [`VulkanoWinitConfig::primary_window_visible`],
+    /// these can't be inserted as a [`SwapchainConfig`] on the primary window before it's created.
+    pub primary_swapchain_config: SwapchainConfig,
+    /// Set once [`VulkanoWindowPlugin`] has taken the config into use, so a config inserted after
+    /// the plugin can be detected
+    used_by_plugin: bool,
 }
 
 impl Default for VulkanoWinitConfig {
@@ -78,51 +129,351 @@ impl Default for VulkanoWinitConfig {
             #[cfg(feature = "gui")]
             is_gui_overlay: true,
             add_primary_window: true,
+            resize_policy: ResizePolicy::Immediate,
+            emulate_mouse_from_touch: false,
+            primary_window_visible: true,
+            primary_swapchain_config: SwapchainConfig::default(),
+            used_by_plugin: false,
         }
     }
 }
 
+/// Controls when window swapchains are recreated after resizing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResizePolicy {
+    /// Recreate the swapchain on the frame after each resize event
+    Immediate,
+    /// Recreate the swapchain once the window hasn't been resized for the given duration. Dragging
+    /// a window edge sends a resize event each frame, and recreating large swapchains for each of
+    /// them can stall.
+    ///
+    /// This only delays recreation on platforms which keep presenting to a swapchain of the old
+    /// size, e.g. Wayland and macOS, where the old images are scaled until the delay passes.
+    /// Vulkano's acquire recreates the swapchain whenever the driver reports it as suboptimal or
+    /// out of date, which X11 and Windows drivers do on each frame of a drag, so there it behaves
+    /// like [`ResizePolicy::Immediate`].
+    ///
+    /// Winit doesn't report when an interactive resize ends, so recreating only on mouse release
+    /// isn't possible. A debounce delay of around 100ms is a close substitute.
+    Debounce(Duration),
+}
+
 /// Wrapper around [`VulkanoContext`] to allow using them as resources
 #[derive(Resource)]
 pub struct BevyVulkanoContext {
     pub context: VulkanoContext,
 }
 
-/// Plugin that allows replacing Bevy's render backend with Vulkano. See examples for usage.
-#[derive(Default)]
-pub struct VulkanoWinitPlugin {
-    pub window_descriptor: Window,
+/// Creates the [`VulkanoContext`] and inserts it as the [`BevyVulkanoContext`] resource, without
+/// creating an event loop or windows. Enough for compute only tools and tests. The configuration
+/// the context was created with is inserted as the [`EffectiveVulkanoConfig`] resource.
+///
+/// Use [`VulkanoContextPlugin::from_context`] to share a context with other libraries instead.
+#[derive(Clone)]
+pub struct VulkanoContextPlugin {
+    vulkano_config: Arc<dyn Fn() -> VulkanoConfig + Send + Sync>,
+    device_selection: DeviceSelection,
+    validation_logging: Option<ValidationLogging>,
+    // Taken when the plugin is built, the context isn't `Clone`
+    context: Arc<Mutex<Option<VulkanoContext>>>,
+}
+
+impl Default for VulkanoContextPlugin {
+    fn default() -> Self {
+        VulkanoContextPlugin {
+            vulkano_config: Arc::new(VulkanoConfig::default),
+            device_selection: DeviceSelection::Default,
+            validation_logging: None,
+            context: Arc::new(Mutex::new(None)),
+        }
+    }
 }
 
-impl Plugin for VulkanoWinitPlugin {
+impl VulkanoContextPlugin {
+    /// Uses an existing context instead of creating one, so other libraries can share its
+    /// instance, device and queues. Hand them clones of [`VulkanoContext::device`] and the queues
+    /// before adding the plugin. The config, device selection including
+    /// [`DEVICE_SELECTION_ENV_VAR`] and validation logging are ignored, with a warning if set.
+    /// Building the plugin panics if the device doesn't enable the extensions and features required
+    /// through [`DeviceRequirements`]. The context's instance must have the extensions needed for
+    /// surfaces if windows are created.
+    ///
+    /// Only a whole [`VulkanoContext`] can be shared, vulkano-util can't create one from an
+    /// existing device and queues.
+    pub fn from_context(context: VulkanoContext) -> Self {
+        VulkanoContextPlugin {
+            context: Arc::new(Mutex::new(Some(context))),
+            ..Default::default()
+        }
+    }
+
+    /// Sets the function creating the instance and device configuration. [`VulkanoConfig`] isn't
+    /// `Send`, so it's created when the plugin is built.
+    pub fn with_vulkano_config(
+        mut self,
+        vulkano_config: impl Fn() -> VulkanoConfig + Send + Sync + 'static,
+    ) -> Self {
+        self.vulkano_config = Arc::new(vulkano_config);
+        self
+    }
+
+    /// Sets which physical device is preferred. [`DEVICE_SELECTION_ENV_VAR`] overrides it if set.
+    pub fn with_device_selection(mut self, device_selection: DeviceSelection) -> Self {
+        self.device_selection = device_selection;
+        self
+    }
+
+    /// Forwards Vulkan validation and performance messages to Bevy's log. Replaces the config's
+    /// [`debug_create_info`](VulkanoConfig::debug_create_info).
+    pub fn with_validation_logging(mut self, validation_logging: ValidationLogging) -> Self {
+        self.validation_logging = Some(validation_logging);
+        self
+    }
+}
+
+impl Plugin for VulkanoContextPlugin {
     fn build(&self, app: &mut App) {
-        // Create event loop, window and renderer (tied together...)
-        let event_loop = EventLoop::new();
+        // Requirements added after this are ignored, e.g. by plugins added later in the same
+        // plugin group. Fetching them once here means only later changes are reported.
+        let mut requirements_state =
+            SystemState::<Option<Res<DeviceRequirements>>>::new(&mut app.world);
+        requirements_state.get(&app.world);
+        app.add_startup_system(
+            (move |world: &mut World| {
+                if requirements_state
+                    .get(world)
+                    .map_or(false, |requirements| requirements.is_changed())
+                {
+                    warn!(
+                        "DeviceRequirements changed after VulkanoContextPlugin created the \
+                         device, add plugins with device requirements before it. The changes are \
+                         ignored"
+                    );
+                }
+            })
+            .in_base_set(StartupSet::PreStartup),
+        );
 
-        // Retrieve config, or use default.
-        let config = if app
-            .world
-            .get_non_send_resource::<VulkanoWinitConfig>()
+        if let Some(context) = self.context.lock().unwrap().take() {
+            if self.device_selection != DeviceSelection::Default
+                || DeviceSelection::from_env().is_some()
+            {
+                warn!("Device selection is ignored with a provided context");
+            }
+            if self.validation_logging.is_some() {
+                warn!(
+                    "Validation logging is ignored with a provided context, set the config's \
+                     debug_create_info when creating it"
+                );
+            }
+            if let Some((extensions, features)) = app
+                .world
+                .get_resource::<DeviceRequirements>()
+                .and_then(|device_requirements| device_requirements.missing_from(context.device()))
+            {
+                panic!(
+                    "The provided context's device doesn't enable the required device extensions \
+                     {:?} and features {:?}",
+                    extensions, features
+                );
+            }
+            info!(
+                "Using provided context with device {}",
+                context.device_name()
+            );
+            app.insert_resource(EffectiveVulkanoConfig::from_context(&context))
+                .insert_resource(BevyVulkanoContext {
+                    context,
+                });
+            return;
+        }
+        // A pre-inserted config takes precedence over the plugin's. Its vulkano config is useless
+        // once the context has been created.
+        let mut vulkano_config = match app.world.get_non_send_resource_mut::<VulkanoWinitConfig>() {
+            Some(mut config) => {
+                warn!(
+                    "Configuring with a pre-inserted VulkanoWinitConfig is deprecated, use the \
+                     plugin's builder methods instead"
+                );
+                std::mem::take(&mut config.vulkano_config)
+            }
+            None => (self.vulkano_config)(),
+        };
+        let device_selection =
+            DeviceSelection::from_env().unwrap_or_else(|| self.device_selection.clone());
+        device_selection.apply(&mut vulkano_config);
+        if let Some(device_requirements) = app.world.get_resource::<DeviceRequirements>() {
+            device_requirements.apply(&mut vulkano_config);
+        }
+        if let Some(validation_logging) = &self.validation_logging {
+            let validation_errors = validation_logging.apply(&mut vulkano_config);
+            if validation_logging.panic_on_error {
+                app.insert_resource(validation_errors)
+                    .add_system(panic_on_validation_error_system.in_base_set(CoreSet::Last));
+            }
+        }
+        if device_selection.is_required()
+            && select_physical_device(
+                &probe_instance(&vulkano_config.instance_create_info),
+                &vulkano_config,
+            )
             .is_none()
         {
-            VulkanoWinitConfig::default()
-        } else {
-            app.world
-                .remove_non_send_resource::<VulkanoWinitConfig>()
-                .unwrap()
-        };
+            panic!(
+                "No physical device matches the required {:?}, available devices: {:?}",
+                device_selection,
+                available_physical_devices()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|device| device.name)
+                    .collect::<Vec<_>>()
+            );
+        }
+        let context = VulkanoContext::new(vulkano_config);
+        let physical_device = context.device().physical_device();
+        if !device_selection.matches(physical_device) {
+            warn!(
+                "No device matches {:?}, using {}",
+                device_selection,
+                context.device_name()
+            );
+        }
+        info!(
+            "Using device {}, type: {:?}",
+            context.device_name(),
+            physical_device.properties().device_type
+        );
+        app.insert_resource(EffectiveVulkanoConfig::from_context(&context))
+            .insert_resource(BevyVulkanoContext {
+                context,
+            });
+    }
+}
 
-        // Create vulkano context using the vulkano config from config
-        let VulkanoWinitConfig {
-            vulkano_config, ..
-        } = config;
-        let vulkano_context = VulkanoContext::new(vulkano_config);
-        // Place config back as resource. Vulkano config will be useless at this point.
-        let new_config = VulkanoWinitConfig {
-            vulkano_config: VulkanoConfig::default(),
-            ..config
-        };
-        app.insert_non_send_resource(new_config);
+/// Creates the winit event loop and windows, and runs the app with it. Requires the
+/// [`BevyVulkanoContext`] resource, add [`VulkanoContextPlugin`] first.
+///
+/// `T` is the user event type of the winit event loop. Background threads can send events through
+/// the [`EventLoopProxy<T>`](winit::event_loop::EventLoopProxy) non-send resource, and they are
+/// forwarded to Bevy as [`Events<T>`].
+pub struct VulkanoWindowPlugin<T: BevyEvent = ()> {
+    pub window_descriptor: Window,
+    return_from_run: bool,
+    #[cfg(feature = "gui")]
+    is_gui_overlay: bool,
+    resize_policy: ResizePolicy,
+    emulate_mouse_from_touch: bool,
+    primary_window_visible: bool,
+    primary_swapchain_config: SwapchainConfig,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl VulkanoWindowPlugin {
+    pub fn new(window_descriptor: Window) -> Self {
+        VulkanoWindowPlugin::with_user_event(window_descriptor)
+    }
+}
+
+impl<T: BevyEvent> VulkanoWindowPlugin<T> {
+    /// Creates the plugin with an event loop that carries user events of type `T`.
+    pub fn with_user_event(window_descriptor: Window) -> Self {
+        let config = VulkanoWinitConfig::default();
+        VulkanoWindowPlugin {
+            window_descriptor,
+            return_from_run: config.return_from_run,
+            #[cfg(feature = "gui")]
+            is_gui_overlay: config.is_gui_overlay,
+            resize_policy: config.resize_policy,
+            emulate_mouse_from_touch: config.emulate_mouse_from_touch,
+            primary_window_visible: config.primary_window_visible,
+            primary_swapchain_config: config.primary_swapchain_config,
+            _marker: PhantomData,
+        }
+    }
+
+    /// See [`VulkanoWinitConfig::return_from_run`]
+    pub fn with_return_from_run(mut self, return_from_run: bool) -> Self {
+        self.return_from_run = return_from_run;
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::is_gui_overlay`]
+    #[cfg(feature = "gui")]
+    pub fn with_gui_overlay(mut self, is_gui_overlay: bool) -> Self {
+        self.is_gui_overlay = is_gui_overlay;
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::resize_policy`]
+    pub fn with_resize_policy(mut self, resize_policy: ResizePolicy) -> Self {
+        self.resize_policy = resize_policy;
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::emulate_mouse_from_touch`]
+    pub fn with_emulate_mouse_from_touch(mut self, emulate_mouse_from_touch: bool) -> Self {
+        self.emulate_mouse_from_touch = emulate_mouse_from_touch;
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::primary_window_visible`]
+    pub fn with_primary_window_visible(mut self, primary_window_visible: bool) -> Self {
+        self.primary_window_visible = primary_window_visible;
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::primary_swapchain_config`]
+    pub fn with_primary_swapchain_config(mut self, swapchain_config: SwapchainConfig) -> Self {
+        self.primary_swapchain_config = swapchain_config;
+        self
+    }
+}
+
+// Derived `Clone` would require `T: Clone`
+impl<T: BevyEvent> Clone for VulkanoWindowPlugin<T> {
+    fn clone(&self) -> Self {
+        VulkanoWindowPlugin {
+            window_descriptor: self.window_descriptor.clone(),
+            return_from_run: self.return_from_run,
+            #[cfg(feature = "gui")]
+            is_gui_overlay: self.is_gui_overlay,
+            resize_policy: self.resize_policy,
+            emulate_mouse_from_touch: self.emulate_mouse_from_touch,
+            primary_window_visible: self.primary_window_visible,
+            primary_swapchain_config: self.primary_swapchain_config.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl Default for VulkanoWindowPlugin {
+    fn default() -> Self {
+        VulkanoWindowPlugin::new(Window::default())
+    }
+}
+
+impl<T: BevyEvent> Plugin for VulkanoWindowPlugin<T> {
+    fn build(&self, app: &mut App) {
+        assert!(
+            app.world.contains_resource::<BevyVulkanoContext>(),
+            "VulkanoWindowPlugin requires BevyVulkanoContext, add VulkanoContextPlugin first"
+        );
+
+        // Create event loop, window and renderer (tied together...)
+        let event_loop = EventLoopBuilder::<T>::with_user_event().build();
+
+        use_config(&mut app.world, || VulkanoWinitConfig {
+            return_from_run: self.return_from_run,
+            #[cfg(feature = "gui")]
+            is_gui_overlay: self.is_gui_overlay,
+            resize_policy: self.resize_policy,
+            emulate_mouse_from_touch: self.emulate_mouse_from_touch,
+            primary_window_visible: self.primary_window_visible,
+            primary_swapchain_config: self.primary_swapchain_config.clone(),
+            ..default()
+        });
+        // Config inserted after this would be ignored by the windows created below
+        app.add_startup_system(warn_on_late_config_system.in_base_set(StartupSet::PreStartup));
 
         let window_plugin = bevy::window::WindowPlugin {
             // This lib controls exiting all on close. (true)
@@ -131,34 +482,228 @@ impl Plugin for VulkanoWinitPlugin {
             ..default()
         };
 
-        // Insert window plugin, vulkano context, windows resource & pipeline data
+        // Insert window plugin, windows resource & pipeline data
         app.add_plugin(window_plugin)
+            .add_event::<T>()
+            .add_event::<WindowKeyboardInput>()
+            .add_event::<WindowMouseButtonInput>()
+            .add_event::<WindowTouchInput>()
+            .add_event::<TouchpadMagnify>()
+            .add_event::<TouchpadRotate>()
+            .add_event::<SmartMagnify>()
+            .add_event::<TouchpadPressure>()
+            .add_event::<AxisMotion>()
+            .add_event::<WindowThemeChanged>()
+            .add_event::<WindowOccluded>()
+            .add_event::<WindowDestroyed>()
+            .add_event::<CursorGrabFailed>()
+            .add_event::<RequestUserAttention>()
             .init_non_send_resource::<BevyVulkanoWindows>()
-            .init_resource::<PipelineSyncData>()
-            .insert_resource(BevyVulkanoContext {
-                context: vulkano_context,
-            });
+            .init_resource::<PipelineSyncData>();
+
+        // The primary window was spawned by the window plugin, apply settings that must be present
+        // before it's created
+        let config = app.world.non_send_resource::<VulkanoWinitConfig>();
+        let primary_window_visible = WindowVisible(config.primary_window_visible);
+        let primary_swapchain_config = config.primary_swapchain_config.clone();
+        let mut primary_window = app.world.query_filtered::<Entity, With<PrimaryWindow>>();
+        if let Ok(entity) = primary_window.get_single(&app.world) {
+            let mut entity = app.world.entity_mut(entity);
+            if !entity.contains::<WindowVisible>() {
+                entity.insert(primary_window_visible);
+            }
+            if !entity.contains::<SwapchainConfig>() {
+                entity.insert(primary_swapchain_config);
+            }
+        }
 
-        // Create initial window
+        // Restore persisted window geometry, then create initial window
+        window_geometry::restore_window_geometry(&mut app.world, &event_loop);
         handle_initial_window_events(&mut app.world, &event_loop);
 
         app.insert_non_send_resource(event_loop)
-            .set_runner(winit_runner)
+            .set_runner(winit_runner::<T>)
+            .add_system(update_swapchain_info_system.in_base_set(CoreSet::First))
             .add_systems(
                 (update_on_resize_system, exit_on_window_close_system)
                     .in_base_set(CoreSet::PreUpdate),
             )
-            .add_system(change_window.in_base_set(CoreSet::PostUpdate));
+            .add_systems(
+                (
+                    change_window,
+                    update_window_icon_system,
+                    update_window_visibility_system,
+                    request_user_attention_system,
+                )
+                    .in_base_set(CoreSet::PostUpdate),
+            );
+    }
+}
+
+/// Inserts the plugin's config, unless one was pre-inserted, which takes precedence over it
+fn use_config(world: &mut World, plugin_config: impl FnOnce() -> VulkanoWinitConfig) {
+    if let Some(mut config) = world.get_non_send_resource_mut::<VulkanoWinitConfig>() {
+        config.used_by_plugin = true;
+    } else {
+        world.insert_non_send_resource(VulkanoWinitConfig {
+            used_by_plugin: true,
+            ..plugin_config()
+        });
+    }
+}
+
+/// Whether the config was replaced after [`VulkanoWindowPlugin`] took it into use. Replacing
+/// keeps the resource's added tick, so only a marker set by the plugin can tell.
+fn is_late_config(config: &VulkanoWinitConfig) -> bool {
+    !config.used_by_plugin
+}
+
+fn warn_on_late_config_system(config: NonSend<VulkanoWinitConfig>) {
+    if is_late_config(&config) {
+        warn!(
+            "VulkanoWinitConfig was inserted after VulkanoWindowPlugin, configure the plugin \
+             instead. Its vulkano config and settings used at window creation are ignored"
+        );
+    }
+}
+
+/// Begins the egui frame of each window. Windows get their gui when they are created, if the `gui`
+/// feature is enabled. Add after [`VulkanoWindowPlugin`].
+#[cfg(feature = "gui")]
+#[derive(Default)]
+pub struct VulkanoGuiPlugin;
+
+#[cfg(feature = "gui")]
+impl Plugin for VulkanoGuiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(begin_egui_frame_system.in_base_set(CoreSet::PreUpdate));
+    }
+}
+
+/// Plugin that allows replacing Bevy's render backend with Vulkano. See examples for usage. Adds
+/// [`VulkanoContextPlugin`], [`VulkanoWindowPlugin`] and, with the `gui` feature,
+/// `VulkanoGuiPlugin`, configured through the builder methods.
+///
+/// `T` is the user event type of the winit event loop, see [`VulkanoWindowPlugin`].
+pub struct VulkanoWinitPlugin<T: BevyEvent = ()> {
+    pub context: VulkanoContextPlugin,
+    pub window: VulkanoWindowPlugin<T>,
+}
+
+impl VulkanoWinitPlugin {
+    pub fn new(window_descriptor: Window) -> Self {
+        VulkanoWinitPlugin::with_user_event(window_descriptor)
+    }
+}
+
+impl<T: BevyEvent> VulkanoWinitPlugin<T> {
+    /// Creates the plugin with an event loop that carries user events of type `T`.
+    pub fn with_user_event(window_descriptor: Window) -> Self {
+        VulkanoWinitPlugin {
+            context: VulkanoContextPlugin::default(),
+            window: VulkanoWindowPlugin::with_user_event(window_descriptor),
+        }
+    }
+
+    /// Runs with an existing context, see [`VulkanoContextPlugin::from_context`]
+    pub fn with_context(mut self, context: VulkanoContext) -> Self {
+        self.context = VulkanoContextPlugin::from_context(context);
+        self
+    }
+
+    /// See [`VulkanoContextPlugin::with_vulkano_config`]
+    pub fn with_vulkano_config(
+        mut self,
+        vulkano_config: impl Fn() -> VulkanoConfig + Send + Sync + 'static,
+    ) -> Self {
+        self.context = self.context.with_vulkano_config(vulkano_config);
+        self
+    }
+
+    /// See [`VulkanoContextPlugin::with_validation_logging`]
+    pub fn with_validation_logging(mut self, validation_logging: ValidationLogging) -> Self {
+        self.context = self.context.with_validation_logging(validation_logging);
+        self
+    }
+
+    /// See [`VulkanoContextPlugin::with_device_selection`]
+    pub fn with_device_selection(mut self, device_selection: DeviceSelection) -> Self {
+        self.context = self.context.with_device_selection(device_selection);
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::return_from_run`]
+    pub fn with_return_from_run(mut self, return_from_run: bool) -> Self {
+        self.window = self.window.with_return_from_run(return_from_run);
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::is_gui_overlay`]
+    #[cfg(feature = "gui")]
+    pub fn with_gui_overlay(mut self, is_gui_overlay: bool) -> Self {
+        self.window = self.window.with_gui_overlay(is_gui_overlay);
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::resize_policy`]
+    pub fn with_resize_policy(mut self, resize_policy: ResizePolicy) -> Self {
+        self.window = self.window.with_resize_policy(resize_policy);
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::emulate_mouse_from_touch`]
+    pub fn with_emulate_mouse_from_touch(mut self, emulate_mouse_from_touch: bool) -> Self {
+        self.window = self
+            .window
+            .with_emulate_mouse_from_touch(emulate_mouse_from_touch);
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::primary_window_visible`]
+    pub fn with_primary_window_visible(mut self, primary_window_visible: bool) -> Self {
+        self.window = self
+            .window
+            .with_primary_window_visible(primary_window_visible);
+        self
+    }
+
+    /// See [`VulkanoWinitConfig::primary_swapchain_config`]
+    pub fn with_primary_swapchain_config(mut self, swapchain_config: SwapchainConfig) -> Self {
+        self.window = self.window.with_primary_swapchain_config(swapchain_config);
+        self
+    }
+}
+
+impl Default for VulkanoWinitPlugin {
+    fn default() -> Self {
+        VulkanoWinitPlugin::new(Window::default())
+    }
+}
+
+impl<T: BevyEvent> Plugin for VulkanoWinitPlugin<T> {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(self.context.clone())
+            .add_plugin(self.window.clone());
 
-        // Add gui begin frame system
         #[cfg(feature = "gui")]
-        {
-            app.add_system_to_stage(CoreStage::PreUpdate, begin_egui_frame_system);
+        app.add_plugin(VulkanoGuiPlugin);
+    }
+}
+
+fn update_swapchain_info_system(
+    windows: NonSend<BevyVulkanoWindows>,
+    mut swapchain_infos: Query<(Entity, &mut SwapchainInfo)>,
+) {
+    for (entity, mut swapchain_info) in swapchain_infos.iter_mut() {
+        if let Some(info) = windows.swapchain_info(entity) {
+            swapchain_info.set_if_neq(info);
         }
     }
 }
 
 fn update_on_resize_system(
+    config: NonSend<VulkanoWinitConfig>,
+    mut pending_resizes: Local<HashMap<Entity, Instant>>,
     mut pipeline_data: ResMut<PipelineSyncData>,
     mut windows: NonSendMut<BevyVulkanoWindows>,
     mut window_resized_events: EventReader<WindowResized>,
@@ -166,9 +711,36 @@ fn update_on_resize_system(
 ) {
     let mut changed_window_ids = HashSet::new();
     changed_window_ids.extend(window_created_events.iter().map(|event| event.window));
-    changed_window_ids.extend(window_resized_events.iter().map(|event| event.window));
+    match config.resize_policy {
+        ResizePolicy::Immediate => {
+            changed_window_ids.extend(window_resized_events.iter().map(|event| event.window));
+        }
+        ResizePolicy::Debounce(delay) => {
+            // Each resize restarts the delay, so swapchain is recreated once the window has
+            // stopped resizing
+            let now = Instant::now();
+            pending_resizes.extend(
+                window_resized_events
+                    .iter()
+                    .map(|event| (event.window, now)),
+            );
+            pending_resizes.retain(|id, resized_at| {
+                if now.duration_since(*resized_at) >= delay {
+                    changed_window_ids.insert(*id);
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+    }
 
     for id in changed_window_ids {
+        // Swapchain can't be created with a zero extent, it's resized again once the window is
+        // restored
+        if windows.is_minimized(id) {
+            continue;
+        }
         #[cfg(not(feature = "gui"))]
         if let Some(window_renderer) = windows.get_window_renderer_mut(id) {
             // Swap chain will be resized at the beginning of next frame. But user should update pipeline frame data
@@ -194,30 +766,194 @@ fn update_on_resize_system(
     }
 }
 
+fn update_window_icon_system(
+    windows: NonSend<BevyVulkanoWindows>,
+    changed_icons: Query<(Entity, &WindowIcon), Changed<WindowIcon>>,
+    mut removed_icons: RemovedComponents<WindowIcon>,
+) {
+    // Also sets the icons of new windows, they are created before the first update
+    for (entity, icon) in changed_icons.iter() {
+        if let Some(winit_window) = windows.get_winit_window(entity) {
+            winit_window.set_window_icon(icon.to_winit_icon());
+        }
+    }
+    for entity in removed_icons.iter() {
+        if let Some(winit_window) = windows.get_winit_window(entity) {
+            winit_window.set_window_icon(None);
+        }
+    }
+}
+
+fn update_window_visibility_system(
+    windows: NonSend<BevyVulkanoWindows>,
+    changed_visibilities: Query<(Entity, &WindowVisible), Changed<WindowVisible>>,
+) {
+    for (entity, visible) in changed_visibilities.iter() {
+        if let Some(winit_window) = windows.get_winit_window(entity) {
+            winit_window.set_visible(visible.0);
+        }
+    }
+}
+
+fn request_user_attention_system(
+    windows: NonSend<BevyVulkanoWindows>,
+    mut request_user_attention_events: EventReader<RequestUserAttention>,
+) {
+    for event in request_user_attention_events.iter() {
+        if let Some(winit_window) = windows.get_winit_window(event.window) {
+            winit_window
+                .request_user_attention(event.attention.map(converters::convert_user_attention));
+        }
+    }
+}
+
 fn change_window(world: &mut World) {
     let mut state: SystemState<(
         NonSendMut<BevyVulkanoWindows>,
         ResMut<PipelineSyncData>,
-        Query<(Entity, &Window)>,
+        Query<(Entity, &mut Window, &mut CachedWindow), Changed<Window>>,
+        Query<(&SurfaceSupport, &mut EffectivePresentMode)>,
         Query<Entity, With<PrimaryWindow>>,
         EventWriter<AppExit>,
         EventWriter<WindowClosed>,
+        EventWriter<WindowScaleFactorChanged>,
+        EventWriter<CursorGrabFailed>,
     )> = SystemState::from_world(world);
 
     let (
         mut vulkano_winit_windows,
         mut pipeline_sync_data,
         mut windows,
+        mut present_modes,
         primary_window_entity,
         mut app_exit_events,
         mut window_closed_events,
+        mut window_scale_factor_changed_events,
+        mut cursor_grab_failed_events,
     ) = state.get_mut(world);
 
     let mut removed_windows = vec![];
 
     // TODO: This is a big one. Bevy doesnt send commands anymore. They are directly linked to winit i beleive
 
-    for (window, bevy_window) in windows.iter_mut() {
+    for (window_entity, mut window, mut cache) in windows.iter_mut() {
+        if let Some(winit_window) = vulkano_winit_windows.get_winit_window(window_entity) {
+            if window.resolution.scale_factor_override()
+                != cache.window.resolution.scale_factor_override()
+            {
+                // Keep the logical size, resize the window to match the new scale factor
+                let physical_size =
+                    winit::dpi::LogicalSize::new(cache.window.width(), cache.window.height())
+                        .to_physical::<u32>(window.scale_factor());
+                window
+                    .resolution
+                    .set_physical_resolution(physical_size.width, physical_size.height);
+                winit_window.set_inner_size(physical_size);
+                window_scale_factor_changed_events.send(WindowScaleFactorChanged {
+                    window: window_entity,
+                    scale_factor: window.scale_factor(),
+                });
+            }
+
+            if window.cursor.icon != cache.window.cursor.icon {
+                winit_window.set_cursor_icon(converters::convert_cursor_icon(window.cursor.icon));
+            }
+
+            if window.cursor.grab_mode != cache.window.cursor.grab_mode {
+                let requested = window.cursor.grab_mode;
+                let applied = match attempt_grab(winit_window, requested) {
+                    Ok(applied) => applied,
+                    Err(err) => {
+                        error!("Unable to set cursor grab mode {:?}: {}", requested, err);
+                        cache.window.cursor.grab_mode
+                    }
+                };
+                if applied != requested {
+                    warn!(
+                        "Cursor grab mode {:?} isn't supported, using {:?}",
+                        requested, applied
+                    );
+                    window.cursor.grab_mode = applied;
+                    cursor_grab_failed_events.send(CursorGrabFailed {
+                        window: window_entity,
+                        requested,
+                        applied,
+                    });
+                }
+            }
+
+            if window.cursor.visible != cache.window.cursor.visible {
+                winit_window.set_cursor_visible(window.cursor.visible);
+            }
+
+            if window.physical_cursor_position() != cache.window.physical_cursor_position() {
+                if let Some(physical_position) = window.physical_cursor_position() {
+                    let inner_size = winit_window.inner_size();
+                    // Flip the coordinate space back to winit's, origin at top left
+                    let position = winit::dpi::PhysicalPosition::new(
+                        physical_position.x,
+                        inner_size.height as f32 - physical_position.y,
+                    );
+                    if let Err(err) = winit_window.set_cursor_position(position) {
+                        error!("Unable to set cursor position: {}", err);
+                    }
+                }
+            }
+
+            if window.window_level != cache.window.window_level {
+                winit_window
+                    .set_window_level(converters::convert_window_level(window.window_level));
+            }
+
+            if let Some(maximized) = window
+                .bypass_change_detection()
+                .internal
+                .take_maximize_request()
+            {
+                winit_window.set_maximized(maximized);
+            }
+
+            if let Some(minimized) = window
+                .bypass_change_detection()
+                .internal
+                .take_minimize_request()
+            {
+                winit_window.set_minimized(minimized);
+            }
+
+            if window.ime_enabled != cache.window.ime_enabled {
+                winit_window.set_ime_allowed(window.ime_enabled);
+            }
+
+            if window.ime_position != cache.window.ime_position {
+                winit_window.set_ime_position(winit::dpi::LogicalPosition::new(
+                    window.ime_position.x,
+                    window.ime_position.y,
+                ));
+            }
+
+            if window.present_mode != cache.window.present_mode {
+                if let Ok((surface_support, mut effective_present_mode)) =
+                    present_modes.get_mut(window_entity)
+                {
+                    let present_mode = negotiate_present_mode(surface_support, window.present_mode);
+                    #[cfg(not(feature = "gui"))]
+                    let window_renderer =
+                        vulkano_winit_windows.get_window_renderer_mut(window_entity);
+                    #[cfg(feature = "gui")]
+                    let window_renderer = vulkano_winit_windows
+                        .get_window_renderer_mut(window_entity)
+                        .map(|(window_renderer, _)| window_renderer);
+                    if let Some(window_renderer) = window_renderer {
+                        window_renderer.set_present_mode(present_mode);
+                        effective_present_mode.0 = present_mode;
+                    }
+                }
+            }
+
+            cache.window = window.clone();
+        }
+
         // for command in bevy_window.drain_commands() {
         //     match command {
         //         bevy::window::WindowCommand::SetWindowMode {
@@ -444,9 +1180,9 @@ fn change_window(world: &mut World) {
     }
 }
 
-fn run<F>(event_loop: EventLoop<()>, event_handler: F) -> !
+fn run<T, F>(event_loop: EventLoop<T>, event_handler: F) -> !
 where
-    F: 'static + FnMut(Event<'_, ()>, &EventLoopWindowTarget<()>, &mut ControlFlow),
+    F: 'static + FnMut(Event<'_, T>, &EventLoopWindowTarget<T>, &mut ControlFlow),
 {
     event_loop.run(event_handler)
 }
@@ -460,9 +1196,9 @@ where
     target_os = "netbsd",
     target_os = "openbsd"
 ))]
-fn run_return<F>(event_loop: &mut EventLoop<()>, event_handler: F) -> i32
+fn run_return<T, F>(event_loop: &mut EventLoop<T>, event_handler: F) -> i32
 where
-    F: FnMut(Event<'_, ()>, &EventLoopWindowTarget<()>, &mut ControlFlow),
+    F: FnMut(Event<'_, T>, &EventLoopWindowTarget<T>, &mut ControlFlow),
 {
     use winit::platform::run_return::EventLoopExtRunReturn;
     event_loop.run_return(event_handler)
@@ -477,21 +1213,61 @@ where
     target_os = "netbsd",
     target_os = "openbsd"
 )))]
-fn run_return<F>(_event_loop: &mut EventLoop<()>, _event_handler: F)
+fn run_return<T, F>(_event_loop: &mut EventLoop<T>, _event_handler: F)
 where
-    F: FnMut(Event<'_, ()>, &EventLoopWindowTarget<()>, &mut ControlFlow),
+    F: FnMut(Event<'_, T>, &EventLoopWindowTarget<T>, &mut ControlFlow),
 {
     panic!("Run return is not supported on this platform!")
 }
 
-pub fn winit_runner(app: App) {
-    winit_runner_with(app);
+#[derive(SystemParam)]
+struct WindowEvents<'w> {
+    window_resized: EventWriter<'w, WindowResized>,
+    window_close_requested: EventWriter<'w, WindowCloseRequested>,
+    window_scale_factor_changed: EventWriter<'w, WindowScaleFactorChanged>,
+    window_backend_scale_factor_changed: EventWriter<'w, WindowBackendScaleFactorChanged>,
+    window_focused: EventWriter<'w, WindowFocused>,
+    window_moved: EventWriter<'w, WindowMoved>,
+    window_theme_changed: EventWriter<'w, WindowThemeChanged>,
+    window_occluded: EventWriter<'w, WindowOccluded>,
+    window_destroyed: EventWriter<'w, WindowDestroyed>,
+}
+
+#[derive(SystemParam)]
+struct InputEvents<'w> {
+    keyboard_input: EventWriter<'w, KeyboardInput>,
+    window_keyboard_input: EventWriter<'w, WindowKeyboardInput>,
+    character_input: EventWriter<'w, ReceivedCharacter>,
+    mouse_button_input: EventWriter<'w, MouseButtonInput>,
+    window_mouse_button_input: EventWriter<'w, WindowMouseButtonInput>,
+    mouse_wheel_input: EventWriter<'w, MouseWheel>,
+    ime_input: EventWriter<'w, Ime>,
+}
+
+#[derive(SystemParam)]
+struct GestureEvents<'w> {
+    touchpad_magnify: EventWriter<'w, TouchpadMagnify>,
+    touchpad_rotate: EventWriter<'w, TouchpadRotate>,
+    smart_magnify: EventWriter<'w, SmartMagnify>,
+    touchpad_pressure: EventWriter<'w, TouchpadPressure>,
+    axis_motion: EventWriter<'w, AxisMotion>,
 }
 
-pub fn winit_runner_with(mut app: App) {
+#[derive(SystemParam)]
+struct CursorEvents<'w> {
+    cursor_moved: EventWriter<'w, CursorMoved>,
+    cursor_entered: EventWriter<'w, CursorEntered>,
+    cursor_left: EventWriter<'w, CursorLeft>,
+}
+
+pub fn winit_runner<T: BevyEvent>(app: App) {
+    winit_runner_with::<T>(app);
+}
+
+pub fn winit_runner_with<T: BevyEvent>(mut app: App) {
     let mut event_loop = app
         .world
-        .remove_non_send_resource::<EventLoop<()>>()
+        .remove_non_send_resource::<EventLoop<T>>()
         .unwrap();
     let mut app_exit_event_reader = ManualEventReader::<AppExit>::default();
     app.world
@@ -503,11 +1279,16 @@ pub fn winit_runner_with(mut app: App) {
         .world
         .get_non_send_resource::<VulkanoWinitConfig>()
         .map_or(false, |config| config.return_from_run);
+    let emulate_mouse_from_touch = app
+        .world
+        .get_non_send_resource::<VulkanoWinitConfig>()
+        .map_or(false, |config| config.emulate_mouse_from_touch);
 
     let mut active = true;
+    let mut touch_mouse_emulation = TouchMouseEmulation::default();
 
-    let event_handler = move |event: Event<()>,
-                              event_loop: &EventLoopWindowTarget<()>,
+    let event_handler = move |event: Event<T>,
+                              event_loop: &EventLoopWindowTarget<T>,
                               control_flow: &mut ControlFlow| {
         *control_flow = ControlFlow::Poll;
 
@@ -563,13 +1344,24 @@ pub fn winit_runner_with(mut app: App) {
                 ..
             } => {
                 let mut state: SystemState<(
-                    NonSend<BevyVulkanoWindows>,
-                    Query<&mut Window>,
+                    NonSendMut<BevyVulkanoWindows>,
+                    Query<(&mut Window, &mut CachedWindow)>,
                     ResMut<Events<TouchInput>>,
+                    EventWriter<WindowTouchInput>,
+                    EventWriter<CursorMoved>,
+                    EventWriter<MouseButtonInput>,
+                    EventWriter<WindowMouseButtonInput>,
                 )> = SystemState::from_world(&mut app.world);
 
-                let (vulkano_winit_windows, mut windows, mut touch_input_events) =
-                    state.get_mut(&mut app.world);
+                let (
+                    mut vulkano_winit_windows,
+                    mut windows,
+                    mut touch_input_events,
+                    mut window_touch_input_events,
+                    mut cursor_moved_events,
+                    mut mouse_button_input_events,
+                    mut window_mouse_button_input_events,
+                ) = state.get_mut(&mut app.world);
 
                 let window_entity = if let Some(window_id) =
                     vulkano_winit_windows.get_window_entity(*winit_window_id)
@@ -583,7 +1375,7 @@ pub fn winit_runner_with(mut app: App) {
                     return;
                 };
 
-                let window = if let Ok(window) = windows.get_mut(window_entity) {
+                let (mut window, mut cache) = if let Ok(window) = windows.get_mut(window_entity) {
                     window
                 } else {
                     warn!("Skipped event for unknown Window Id {:?}", winit_window_id);
@@ -597,18 +1389,60 @@ pub fn winit_runner_with(mut app: App) {
                         // On a mobile window, the start is from the top while on PC/Linux/OSX from
                         // bottom
                         if cfg!(target_os = "android") || cfg!(target_os = "ios") {
-                            let window_height = windows.iter().next().unwrap().height();
-                            location.y = window_height - location.y;
+                            location.y = window.height() - location.y;
                         }
-                        let mut touch = converters::convert_touch_input(*touch, location);
+                        let mut touch_input = converters::convert_touch_input(*touch, location);
 
                         // We want to cancel any event when skip_window_event is true
                         if cfg!(feature = "gui") && skip_window_event {
-                            touch.phase = bevy::input::touch::TouchPhase::Cancelled;
-                            touch_input_events.send(touch);
-                        } else {
-                            touch_input_events.send(touch);
+                            touch_input.phase = bevy::input::touch::TouchPhase::Cancelled;
                         }
+
+                        if emulate_mouse_from_touch {
+                            let (moves_cursor, button_state) =
+                                touch_mouse_emulation.update(&touch_input);
+                            if moves_cursor {
+                                // Same as cursor moved, origin at bottom left
+                                let physical_position = Vec2::new(
+                                    touch.location.x as f32,
+                                    window.physical_height() as f32 - touch.location.y as f32,
+                                );
+                                window.set_physical_cursor_position(Some(
+                                    physical_position.as_dvec2(),
+                                ));
+                                cache.window.set_physical_cursor_position(Some(
+                                    physical_position.as_dvec2(),
+                                ));
+                                cursor_moved_events.send(CursorMoved {
+                                    window: window_entity,
+                                    position: (physical_position.as_dvec2()
+                                        / window.scale_factor())
+                                    .as_vec2(),
+                                });
+                            }
+                            if let Some(state) = button_state {
+                                vulkano_winit_windows
+                                    .held_inputs
+                                    .entry(window_entity)
+                                    .or_default()
+                                    .track_button(MouseButton::Left, state);
+                                let input = MouseButtonInput {
+                                    button: MouseButton::Left,
+                                    state,
+                                };
+                                window_mouse_button_input_events.send(WindowMouseButtonInput {
+                                    window: window_entity,
+                                    input,
+                                });
+                                mouse_button_input_events.send(input);
+                            }
+                        }
+
+                        window_touch_input_events.send(WindowTouchInput {
+                            window: window_entity,
+                            input: touch_input,
+                        });
+                        touch_input_events.send(touch_input);
                     }
                     _ => (),
                 }
@@ -626,39 +1460,26 @@ pub fn winit_runner_with(mut app: App) {
                 } => {
                     let mut state: SystemState<(
                         NonSendMut<BevyVulkanoWindows>,
-                        Query<&mut Window>,
-                        EventWriter<WindowResized>,
-                        EventWriter<WindowFocused>,
-                        EventWriter<WindowMoved>,
-                        EventWriter<WindowCloseRequested>,
-                        EventWriter<KeyboardInput>,
-                        EventWriter<CursorMoved>,
-                        EventWriter<CursorEntered>,
-                        EventWriter<CursorLeft>,
-                        EventWriter<MouseButtonInput>,
-                        EventWriter<MouseWheel>,
-                        EventWriter<ReceivedCharacter>,
-                        EventWriter<WindowBackendScaleFactorChanged>,
-                        EventWriter<WindowScaleFactorChanged>,
+                        Query<(
+                            &mut Window,
+                            &mut CachedWindow,
+                            &mut KeyboardModifiers,
+                            &mut WindowState,
+                        )>,
+                        WindowEvents,
+                        InputEvents,
+                        GestureEvents,
+                        CursorEvents,
                         ResMut<Events<FileDragAndDrop>>,
                     )> = SystemState::from_world(&mut app.world);
 
                     let (
-                        vulkano_winit_windows,
+                        mut vulkano_winit_windows,
                         mut windows,
-                        mut resize_events,
-                        mut focused_events,
-                        mut moved_events,
-                        mut window_close_requested_events,
-                        mut keyboard_input_events,
-                        mut cursor_moved_events,
-                        mut cursor_entered_events,
-                        mut cursor_left_events,
-                        mut mouse_button_input_events,
-                        mut mouse_wheel_events,
-                        mut received_character_events,
-                        mut window_backend_scale_factor_changed_events,
-                        mut window_scale_factor_changed_events,
+                        mut window_events,
+                        mut input_events,
+                        mut gesture_events,
+                        mut cursor_events,
                         mut file_drag_and_drop_events,
                     ) = state.get_mut(&mut app.world);
 
@@ -674,12 +1495,13 @@ pub fn winit_runner_with(mut app: App) {
                         return;
                     };
 
-                    let mut window = if let Ok(window) = windows.get_mut(window_entity) {
-                        window
-                    } else {
-                        warn!("Skipped event for unknown Window Id {:?}", winit_window_id);
-                        return;
-                    };
+                    let (mut window, mut cache, mut modifiers, mut window_state) =
+                        if let Ok(window) = windows.get_mut(window_entity) {
+                            window
+                        } else {
+                            warn!("Skipped event for unknown Window Id {:?}", winit_window_id);
+                            return;
+                        };
 
                     match event {
                         WindowEvent::Resized(size) => {
@@ -687,21 +1509,54 @@ pub fn winit_runner_with(mut app: App) {
                                 .resolution
                                 .set_physical_resolution(size.width, size.height);
 
-                            resize_events.send(WindowResized {
+                            // Minimized windows report a zero size
+                            window_state.minimized = size.width == 0 || size.height == 0;
+                            window_state.maximized = vulkano_winit_windows
+                                .get_winit_window(window_entity)
+                                .map_or(false, |winit_window| winit_window.is_maximized());
+                            if window_state.minimized {
+                                vulkano_winit_windows.minimized.insert(window_entity);
+                            } else {
+                                vulkano_winit_windows.minimized.remove(&window_entity);
+                            }
+
+                            window_events.window_resized.send(WindowResized {
                                 window: window_entity,
                                 width: window.width(),
                                 height: window.height(),
                             });
                         }
                         WindowEvent::CloseRequested => {
-                            window_close_requested_events.send(WindowCloseRequested {
-                                window: window_entity,
-                            });
+                            window_events
+                                .window_close_requested
+                                .send(WindowCloseRequested {
+                                    window: window_entity,
+                                });
                         }
+                        // Synthetic events are sent on focus changes and ignored. Held keys are
+                        // released on focus loss below, and keys held while gaining focus shouldn't
+                        // count as pressed.
                         WindowEvent::KeyboardInput {
-                            ref input, ..
+                            ref input,
+                            is_synthetic: false,
+                            ..
                         } => {
-                            keyboard_input_events.send(converters::convert_keyboard_input(input));
+                            let input = converters::convert_keyboard_input(input);
+                            vulkano_winit_windows
+                                .held_inputs
+                                .entry(window_entity)
+                                .or_default()
+                                .track_key(&input);
+                            input_events
+                                .window_keyboard_input
+                                .send(WindowKeyboardInput {
+                                    window: window_entity,
+                                    input,
+                                });
+                            input_events.keyboard_input.send(input);
+                        }
+                        WindowEvent::ModifiersChanged(modifiers_state) => {
+                            *modifiers = converters::convert_modifiers_state(modifiers_state);
                         }
                         WindowEvent::CursorMoved {
                             position, ..
@@ -715,9 +1570,13 @@ pub fn winit_runner_with(mut app: App) {
                             let y_position = inner_size.height as f64 - position.y;
 
                             let physical_position = Vec2::new(position.x as f32, y_position as f32);
-                            window.set_cursor_position(Some(physical_position));
+                            window.set_physical_cursor_position(Some(physical_position.as_dvec2()));
+                            // The cursor is already there, don't move it on window change
+                            cache
+                                .window
+                                .set_physical_cursor_position(Some(physical_position.as_dvec2()));
 
-                            cursor_moved_events.send(CursorMoved {
+                            cursor_events.cursor_moved.send(CursorMoved {
                                 window: window_entity,
                                 position: (physical_position.as_dvec2() / window.scale_factor())
                                     .as_vec2(),
@@ -726,15 +1585,16 @@ pub fn winit_runner_with(mut app: App) {
                         WindowEvent::CursorEntered {
                             ..
                         } => {
-                            cursor_entered_events.send(CursorEntered {
+                            cursor_events.cursor_entered.send(CursorEntered {
                                 window: window_entity,
                             });
                         }
                         WindowEvent::CursorLeft {
                             ..
                         } => {
-                            window.set_cursor_position(None);
-                            cursor_left_events.send(CursorLeft {
+                            window.set_physical_cursor_position(None);
+                            cache.window.set_physical_cursor_position(None);
+                            cursor_events.cursor_left.send(CursorLeft {
                                 window: window_entity,
                             });
                         }
@@ -743,31 +1603,92 @@ pub fn winit_runner_with(mut app: App) {
                             button,
                             ..
                         } => {
-                            mouse_button_input_events.send(MouseButtonInput {
-                                button: converters::convert_mouse_button(button),
-                                state: converters::convert_element_state(state),
-                            });
+                            let button = converters::convert_mouse_button(button);
+                            let state = converters::convert_element_state(state);
+                            vulkano_winit_windows
+                                .held_inputs
+                                .entry(window_entity)
+                                .or_default()
+                                .track_button(button, state);
+                            let input = MouseButtonInput {
+                                button,
+                                state,
+                            };
+                            input_events
+                                .window_mouse_button_input
+                                .send(WindowMouseButtonInput {
+                                    window: window_entity,
+                                    input,
+                                });
+                            input_events.mouse_button_input.send(input);
                         }
                         WindowEvent::MouseWheel {
                             delta, ..
                         } => match delta {
                             event::MouseScrollDelta::LineDelta(x, y) => {
-                                mouse_wheel_events.send(MouseWheel {
+                                input_events.mouse_wheel_input.send(MouseWheel {
                                     unit: MouseScrollUnit::Line,
                                     x,
                                     y,
                                 });
                             }
                             event::MouseScrollDelta::PixelDelta(p) => {
-                                mouse_wheel_events.send(MouseWheel {
+                                input_events.mouse_wheel_input.send(MouseWheel {
                                     unit: MouseScrollUnit::Pixel,
                                     x: p.x as f32,
                                     y: p.y as f32,
                                 });
                             }
                         },
+                        WindowEvent::TouchpadMagnify {
+                            delta,
+                            phase,
+                            ..
+                        } => {
+                            gesture_events.touchpad_magnify.send(
+                                converters::convert_touchpad_magnify(window_entity, delta, phase),
+                            );
+                        }
+                        WindowEvent::TouchpadRotate {
+                            delta,
+                            phase,
+                            ..
+                        } => {
+                            gesture_events.touchpad_rotate.send(
+                                converters::convert_touchpad_rotate(window_entity, delta, phase),
+                            );
+                        }
+                        WindowEvent::SmartMagnify {
+                            ..
+                        } => {
+                            gesture_events
+                                .smart_magnify
+                                .send(converters::convert_smart_magnify(window_entity));
+                        }
+                        WindowEvent::TouchpadPressure {
+                            pressure,
+                            stage,
+                            ..
+                        } => {
+                            gesture_events.touchpad_pressure.send(
+                                converters::convert_touchpad_pressure(
+                                    window_entity,
+                                    pressure,
+                                    stage,
+                                ),
+                            );
+                        }
+                        WindowEvent::AxisMotion {
+                            axis,
+                            value,
+                            ..
+                        } => {
+                            gesture_events
+                                .axis_motion
+                                .send(converters::convert_axis_motion(window_entity, axis, value));
+                        }
                         WindowEvent::ReceivedCharacter(c) => {
-                            received_character_events.send(ReceivedCharacter {
+                            input_events.character_input.send(ReceivedCharacter {
                                 window: window_entity,
                                 char: c,
                             })
@@ -776,7 +1697,7 @@ pub fn winit_runner_with(mut app: App) {
                             scale_factor,
                             new_inner_size,
                         } => {
-                            window_backend_scale_factor_changed_events.send(
+                            window_events.window_backend_scale_factor_changed.send(
                                 WindowBackendScaleFactorChanged {
                                     window: window_entity,
                                     scale_factor,
@@ -792,16 +1713,16 @@ pub fn winit_runner_with(mut app: App) {
                                 // Otherwise, use the OS suggested size
                                 // We have already told the OS about our resize constraints, so
                                 // the new_inner_size should take those into account
-                                // *new_inner_size = winit::dpi::LogicalSize::new(
-                                //     window.requested_width(),
-                                //     window.requested_height(),
-                                // )
-                                // .to_physical::<u32>(forced_factor);
+                                *new_inner_size =
+                                    winit::dpi::LogicalSize::new(window.width(), window.height())
+                                        .to_physical::<u32>(forced_factor);
                             } else if approx::relative_ne!(new_factor, prior_factor) {
-                                window_scale_factor_changed_events.send(WindowScaleFactorChanged {
-                                    window: window_entity,
-                                    scale_factor,
-                                });
+                                window_events.window_scale_factor_changed.send(
+                                    WindowScaleFactorChanged {
+                                        window: window_entity,
+                                        scale_factor,
+                                    },
+                                );
                             }
 
                             let new_logical_width = new_inner_size.width as f64 / new_factor;
@@ -809,7 +1730,7 @@ pub fn winit_runner_with(mut app: App) {
                             if approx::relative_ne!(window.width() as f64, new_logical_width)
                                 || approx::relative_ne!(window.height() as f64, new_logical_height)
                             {
-                                resize_events.send(WindowResized {
+                                window_events.window_resized.send(WindowResized {
                                     window: window_entity,
                                     width: new_logical_width as f32,
                                     height: new_logical_height as f32,
@@ -821,9 +1742,43 @@ pub fn winit_runner_with(mut app: App) {
                                 new_inner_size.height,
                             )
                         }
+                        WindowEvent::Ime(event) => match event {
+                            event::Ime::Preedit(value, cursor) => {
+                                input_events.ime_input.send(Ime::Preedit {
+                                    window: window_entity,
+                                    value,
+                                    cursor,
+                                });
+                            }
+                            event::Ime::Commit(value) => input_events.ime_input.send(Ime::Commit {
+                                window: window_entity,
+                                value,
+                            }),
+                            event::Ime::Enabled => input_events.ime_input.send(Ime::Enabled {
+                                window: window_entity,
+                            }),
+                            event::Ime::Disabled => input_events.ime_input.send(Ime::Disabled {
+                                window: window_entity,
+                            }),
+                        },
                         WindowEvent::Focused(focused) => {
                             window.focused = focused;
-                            focused_events.send(WindowFocused {
+                            if !focused {
+                                *modifiers = KeyboardModifiers::default();
+                                if let Some(held_inputs) =
+                                    vulkano_winit_windows.held_inputs.get_mut(&window_entity)
+                                {
+                                    release_held_inputs(
+                                        window_entity,
+                                        held_inputs,
+                                        &mut input_events.keyboard_input,
+                                        &mut input_events.window_keyboard_input,
+                                        &mut input_events.mouse_button_input,
+                                        &mut input_events.window_mouse_button_input,
+                                    );
+                                }
+                            }
+                            window_events.window_focused.send(WindowFocused {
                                 window: window_entity,
                                 focused,
                             });
@@ -845,11 +1800,40 @@ pub fn winit_runner_with(mut app: App) {
                                 window: window_entity,
                             });
                         }
+                        WindowEvent::ThemeChanged(theme) => {
+                            let theme = converters::convert_theme(theme);
+                            window_state.theme = Some(theme);
+                            window_events.window_theme_changed.send(WindowThemeChanged {
+                                window: window_entity,
+                                theme,
+                            });
+                        }
+                        WindowEvent::Occluded(occluded) => {
+                            window_state.occluded = occluded;
+                            if occluded {
+                                vulkano_winit_windows.occluded.insert(window_entity);
+                            } else {
+                                vulkano_winit_windows.occluded.remove(&window_entity);
+                            }
+                            window_events.window_occluded.send(WindowOccluded {
+                                window: window_entity,
+                                occluded,
+                            });
+                        }
+                        WindowEvent::Destroyed => {
+                            vulkano_winit_windows.entity_to_winit.remove(&window_entity);
+                            vulkano_winit_windows
+                                .winit_to_entity
+                                .remove(&winit_window_id);
+                            window_events.window_destroyed.send(WindowDestroyed {
+                                window: window_entity,
+                            });
+                        }
                         WindowEvent::Moved(position) => {
                             let position = ivec2(position.x, position.y);
                             window.position = bevy::prelude::WindowPosition::At(position);
 
-                            moved_events.send(WindowMoved {
+                            window_events.window_moved.send(WindowMoved {
                                 entity: window_entity,
                                 position,
                             });
@@ -870,6 +1854,9 @@ pub fn winit_runner_with(mut app: App) {
                         delta: Vec2::new(delta.0 as f32, delta.1 as f32),
                     });
                 }
+                event::Event::UserEvent(user_event) => {
+                    app.world.send_event(user_event);
+                }
                 event::Event::Suspended => {
                     active = false;
                 }
@@ -893,7 +1880,7 @@ pub fn winit_runner_with(mut app: App) {
     }
 }
 
-fn handle_create_window_events(world: &mut World, event_loop: &EventLoopWindowTarget<()>) {
+fn handle_create_window_events<T>(world: &mut World, event_loop: &EventLoopWindowTarget<T>) {
     let mut handle_create_window_events_state: SystemState<(
         Commands,
         Res<BevyVulkanoContext>,
@@ -937,13 +1924,18 @@ fn handle_create_window_events(world: &mut World, event_loop: &EventLoopWindowTa
     handle_create_window_events_state.apply(world);
 }
 
-fn handle_initial_window_events(world: &mut World, event_loop: &EventLoop<()>) {
+fn handle_initial_window_events<T>(world: &mut World, event_loop: &EventLoop<T>) {
     let mut handle_initial_window_events_state: SystemState<(
         Commands,
         Res<BevyVulkanoContext>,
         NonSend<VulkanoWinitConfig>,
         NonSendMut<BevyVulkanoWindows>,
-        Query<(Entity, &Window)>,
+        Query<(
+            Entity,
+            &Window,
+            Option<&SwapchainConfig>,
+            Option<&WindowVisible>,
+        )>,
         EventWriter<WindowCreated>,
     )> = SystemState::from_world(world);
 
@@ -956,7 +1948,7 @@ fn handle_initial_window_events(world: &mut World, event_loop: &EventLoop<()>) {
         mut event_writer,
     ) = handle_initial_window_events_state.get_mut(world);
 
-    for (entity, window) in new_windows.iter() {
+    for (entity, window, swapchain_config, visible) in new_windows.iter() {
         let window = vulkano_winit_windows.create_window(
             &mut commands,
             event_loop,
@@ -964,9 +1956,12 @@ fn handle_initial_window_events(world: &mut World, event_loop: &EventLoop<()>) {
             window,
             &vulkano_context.context,
             &vulkano_config,
+            swapchain_config,
+            visible.copied(),
         );
 
-        commands.spawn(window);
+        // Update the window with the backend's scale factor, size and position
+        commands.entity(entity).insert(window);
 
         event_writer.send(WindowCreated {
             window: entity,
@@ -976,6 +1971,36 @@ fn handle_initial_window_events(world: &mut World, event_loop: &EventLoop<()>) {
     handle_initial_window_events_state.apply(world);
 }
 
+/// Sends release events for the keys and mouse buttons held in the window, e.g. when it loses
+/// focus or is closed, so that they don't stay pressed
+fn release_held_inputs(
+    window: Entity,
+    held_inputs: &mut HeldInputs,
+    keyboard_input: &mut EventWriter<KeyboardInput>,
+    window_keyboard_input: &mut EventWriter<WindowKeyboardInput>,
+    mouse_button_input: &mut EventWriter<MouseButtonInput>,
+    window_mouse_button_input: &mut EventWriter<WindowMouseButtonInput>,
+) {
+    for input in held_inputs.release_keys() {
+        window_keyboard_input.send(WindowKeyboardInput {
+            window,
+            input,
+        });
+        keyboard_input.send(input);
+    }
+    for button in held_inputs.release_buttons() {
+        let input = MouseButtonInput {
+            button,
+            state: ButtonState::Released,
+        };
+        window_mouse_button_input.send(WindowMouseButtonInput {
+            window,
+            input,
+        });
+        mouse_button_input.send(input);
+    }
+}
+
 pub fn exit_on_window_close_system(
     mut app_exit_events: EventWriter<AppExit>,
     mut windows: NonSendMut<BevyVulkanoWindows>,
@@ -983,8 +2008,22 @@ pub fn exit_on_window_close_system(
     mut window_close_events: EventWriter<WindowClosed>,
     primary_window_entity: Query<Entity, With<PrimaryWindow>>,
     mut window_close_requested_events: EventReader<WindowCloseRequested>,
+    mut keyboard_input: EventWriter<KeyboardInput>,
+    mut window_keyboard_input: EventWriter<WindowKeyboardInput>,
+    mut mouse_button_input: EventWriter<MouseButtonInput>,
+    mut window_mouse_button_input: EventWriter<WindowMouseButtonInput>,
 ) {
     for event in window_close_requested_events.iter() {
+        if let Some(mut held_inputs) = windows.held_inputs.remove(&event.window) {
+            release_held_inputs(
+                event.window,
+                &mut held_inputs,
+                &mut keyboard_input,
+                &mut window_keyboard_input,
+                &mut mouse_button_input,
+                &mut window_mouse_button_input,
+            );
+        }
         let (app_close, window_close) = close_window(
             event.window,
             &mut windows,
@@ -1027,6 +2066,10 @@ fn close_window(
     };
 
     pipeline_data.remove(window_entity);
+    windows.held_inputs.remove(&window_entity);
+    windows.frames_in_flight.remove(&window_entity);
+    windows.occluded.remove(&window_entity);
+    windows.minimized.remove(&window_entity);
     windows.windows.remove(&winit_id);
     (false, true)
 }
@@ -1037,3 +2080,43 @@ pub fn begin_egui_frame_system(mut vulkano_windows: NonSendMut<BevyVulkanoWindow
         g.begin_frame();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_late(world: &World) -> bool {
+        is_late_config(world.non_send_resource::<VulkanoWinitConfig>())
+    }
+
+    #[test]
+    fn config_inserted_after_plugin_is_late() {
+        let mut world = World::new();
+        use_config(&mut world, VulkanoWinitConfig::default);
+        assert!(!is_late(&world));
+
+        world.insert_non_send_resource(VulkanoWinitConfig::default());
+        assert!(is_late(&world));
+    }
+
+    #[test]
+    fn mutated_or_pre_inserted_config_is_not_late() {
+        let mut world = World::new();
+        world.insert_non_send_resource(VulkanoWinitConfig {
+            resize_policy: ResizePolicy::Debounce(Duration::from_millis(100)),
+            ..default()
+        });
+        use_config(&mut world, VulkanoWinitConfig::default);
+        assert_eq!(
+            world
+                .non_send_resource::<VulkanoWinitConfig>()
+                .resize_policy,
+            ResizePolicy::Debounce(Duration::from_millis(100))
+        );
+
+        world
+            .non_send_resource_mut::<VulkanoWinitConfig>()
+            .emulate_mouse_from_touch = true;
+        assert!(!is_late(&world));
+    }
+}
@@ -0,0 +1,281 @@
+use bevy::{log::warn, prelude::Component};
+use vulkano::{
+    device::physical::{PhysicalDevice, PhysicalDeviceError},
+    format::Format,
+    image::ImageUsage,
+    swapchain::{ColorSpace, CompositeAlpha, PresentMode, Surface, SurfaceInfo},
+};
+
+/// What a window's surface supports, inserted on window entities when the window is created.
+/// Use this to only offer valid present modes and formats, e.g. in settings menus. Image extents
+/// change with the window and monitor, use [`BevyVulkanoWindows::surface_support`] for up to date
+/// values.
+///
+/// [`BevyVulkanoWindows::surface_support`]: crate::BevyVulkanoWindows::surface_support
+#[derive(Debug, Clone, Component)]
+pub struct SurfaceSupport {
+    /// Supported swapchain image formats and their color spaces
+    pub formats: Vec<(Format, ColorSpace)>,
+    pub present_modes: Vec<PresentMode>,
+    pub composite_alpha: Vec<CompositeAlpha>,
+    pub min_image_count: u32,
+    /// `None` if there is no limit. `Some(0)`, Vulkan's value for no limit, is treated the same.
+    pub max_image_count: Option<u32>,
+    pub min_image_extent: [u32; 2],
+    pub max_image_extent: [u32; 2],
+    /// Supported usages of swapchain images
+    pub image_usage: ImageUsage,
+}
+
+impl SurfaceSupport {
+    /// Fails if the surface or device was lost
+    pub(crate) fn query(
+        physical_device: &PhysicalDevice,
+        surface: &Surface,
+    ) -> Result<SurfaceSupport, PhysicalDeviceError> {
+        let capabilities = physical_device.surface_capabilities(surface, SurfaceInfo::default())?;
+        Ok(SurfaceSupport {
+            formats: physical_device.surface_formats(surface, SurfaceInfo::default())?,
+            present_modes: physical_device.surface_present_modes(surface)?.collect(),
+            composite_alpha: capabilities.supported_composite_alpha.into_iter().collect(),
+            min_image_count: capabilities.min_image_count,
+            max_image_count: capabilities.max_image_count,
+            min_image_extent: capabilities.min_image_extent,
+            max_image_extent: capabilities.max_image_extent,
+            image_usage: capabilities.supported_usage_flags,
+        })
+    }
+
+    pub fn supports_present_mode(&self, present_mode: PresentMode) -> bool {
+        self.present_modes.contains(&present_mode)
+    }
+
+    pub fn supports_format(&self, format: Format) -> bool {
+        self.formats.iter().any(|(f, _)| *f == format)
+    }
+
+    /// Whether any format supports the color space, e.g. [`ColorSpace::Hdr10St2084`] for HDR
+    pub fn supports_color_space(&self, color_space: ColorSpace) -> bool {
+        self.formats.iter().any(|(_, c)| *c == color_space)
+    }
+
+    pub fn supports_composite_alpha(&self, composite_alpha: CompositeAlpha) -> bool {
+        self.composite_alpha.contains(&composite_alpha)
+    }
+
+    /// Picks the first supported present mode in order of preference for the requested mode, e.g.
+    /// `Mailbox`, `Immediate` then `Fifo` for [`AutoNoVsync`](bevy::window::PresentMode::AutoNoVsync).
+    /// Falls back to `Fifo`, which is always supported.
+    pub fn select_present_mode(&self, present_mode: bevy::window::PresentMode) -> PresentMode {
+        let preferences: &[PresentMode] = match present_mode {
+            bevy::window::PresentMode::AutoVsync => &[PresentMode::FifoRelaxed, PresentMode::Fifo],
+            bevy::window::PresentMode::AutoNoVsync => &[
+                PresentMode::Mailbox,
+                PresentMode::Immediate,
+                PresentMode::Fifo,
+            ],
+            bevy::window::PresentMode::Fifo => &[PresentMode::Fifo],
+            bevy::window::PresentMode::Immediate => &[PresentMode::Immediate],
+            bevy::window::PresentMode::Mailbox => &[PresentMode::Mailbox],
+        };
+        preferences
+            .iter()
+            .copied()
+            .find(|present_mode| self.supports_present_mode(*present_mode))
+            .unwrap_or(PresentMode::Fifo)
+    }
+
+    /// Picks the composite alpha for a window. An explicitly requested
+    /// [`Window::composite_alpha_mode`](bevy::window::Window::composite_alpha_mode) is used if
+    /// supported. Otherwise transparent windows prefer `PreMultiplied`, then `PostMultiplied` and
+    /// `Inherit`, opaque windows prefer `Opaque`. Falls back to the first supported mode.
+    pub fn select_composite_alpha(
+        &self,
+        composite_alpha_mode: bevy::window::CompositeAlphaMode,
+        transparent: bool,
+    ) -> CompositeAlpha {
+        let requested = match composite_alpha_mode {
+            bevy::window::CompositeAlphaMode::Auto => None,
+            bevy::window::CompositeAlphaMode::Opaque => Some(CompositeAlpha::Opaque),
+            bevy::window::CompositeAlphaMode::PreMultiplied => Some(CompositeAlpha::PreMultiplied),
+            bevy::window::CompositeAlphaMode::PostMultiplied => {
+                Some(CompositeAlpha::PostMultiplied)
+            }
+            bevy::window::CompositeAlphaMode::Inherit => Some(CompositeAlpha::Inherit),
+        };
+        if let Some(requested) = requested {
+            if self.supports_composite_alpha(requested) {
+                return requested;
+            }
+            warn!(
+                "Composite alpha {:?} isn't supported by the surface, selecting automatically",
+                requested
+            );
+        }
+        let preferences: &[CompositeAlpha] = if transparent {
+            &[
+                CompositeAlpha::PreMultiplied,
+                CompositeAlpha::PostMultiplied,
+                CompositeAlpha::Inherit,
+            ]
+        } else {
+            &[CompositeAlpha::Opaque, CompositeAlpha::Inherit]
+        };
+        preferences
+            .iter()
+            .copied()
+            .find(|composite_alpha| self.supports_composite_alpha(*composite_alpha))
+            .or_else(|| self.composite_alpha.first().copied())
+            .unwrap_or(CompositeAlpha::Opaque)
+    }
+
+    /// Clamps an image count to the supported range
+    pub fn clamp_image_count(&self, image_count: u32) -> u32 {
+        let image_count = image_count.max(self.min_image_count);
+        match self.max_image_count {
+            Some(max_image_count) if max_image_count > 0 => image_count.min(max_image_count),
+            _ => image_count,
+        }
+    }
+}
+
+/// The present mode used by the window's swapchain, selected from the requested
+/// [`Window::present_mode`](bevy::window::Window::present_mode) with
+/// [`SurfaceSupport::select_present_mode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+pub struct EffectivePresentMode(pub PresentMode);
+
+/// Selects the present mode for the window, warning if an explicitly requested mode isn't supported
+pub(crate) fn negotiate_present_mode(
+    surface_support: &SurfaceSupport,
+    present_mode: bevy::window::PresentMode,
+) -> PresentMode {
+    let selected = surface_support.select_present_mode(present_mode);
+    let fell_back = match present_mode {
+        bevy::window::PresentMode::Immediate => selected != PresentMode::Immediate,
+        bevy::window::PresentMode::Mailbox => selected != PresentMode::Mailbox,
+        _ => false,
+    };
+    if fell_back {
+        warn!(
+            "Present mode {:?} isn't supported by the surface, using {:?}",
+            present_mode, selected
+        );
+    }
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn surface_support() -> SurfaceSupport {
+        SurfaceSupport {
+            formats: vec![(Format::B8G8R8A8_SRGB, ColorSpace::SrgbNonLinear)],
+            present_modes: vec![PresentMode::Fifo],
+            composite_alpha: vec![CompositeAlpha::Opaque],
+            min_image_count: 2,
+            max_image_count: Some(4),
+            min_image_extent: [1, 1],
+            max_image_extent: [4096, 4096],
+            image_usage: ImageUsage::COLOR_ATTACHMENT,
+        }
+    }
+
+    #[test]
+    fn present_mode_preferences() {
+        let mut support = surface_support();
+        support.present_modes = vec![
+            PresentMode::Fifo,
+            PresentMode::Immediate,
+            PresentMode::Mailbox,
+        ];
+        assert_eq!(
+            support.select_present_mode(bevy::window::PresentMode::AutoNoVsync),
+            PresentMode::Mailbox
+        );
+        assert_eq!(
+            support.select_present_mode(bevy::window::PresentMode::AutoVsync),
+            PresentMode::Fifo
+        );
+        support.present_modes = vec![PresentMode::Fifo, PresentMode::Immediate];
+        assert_eq!(
+            support.select_present_mode(bevy::window::PresentMode::AutoNoVsync),
+            PresentMode::Immediate
+        );
+    }
+
+    #[test]
+    fn unsupported_present_mode_falls_back_to_fifo() {
+        let support = surface_support();
+        assert_eq!(
+            support.select_present_mode(bevy::window::PresentMode::Mailbox),
+            PresentMode::Fifo
+        );
+        assert_eq!(
+            negotiate_present_mode(&support, bevy::window::PresentMode::Immediate),
+            PresentMode::Fifo
+        );
+    }
+
+    #[test]
+    fn composite_alpha_for_transparent_windows() {
+        let mut support = surface_support();
+        support.composite_alpha = vec![
+            CompositeAlpha::Opaque,
+            CompositeAlpha::PostMultiplied,
+            CompositeAlpha::Inherit,
+        ];
+        assert_eq!(
+            support.select_composite_alpha(bevy::window::CompositeAlphaMode::Auto, true),
+            CompositeAlpha::PostMultiplied
+        );
+        assert_eq!(
+            support.select_composite_alpha(bevy::window::CompositeAlphaMode::Auto, false),
+            CompositeAlpha::Opaque
+        );
+    }
+
+    #[test]
+    fn requested_composite_alpha() {
+        let mut support = surface_support();
+        support.composite_alpha = vec![CompositeAlpha::Opaque, CompositeAlpha::Inherit];
+        assert_eq!(
+            support.select_composite_alpha(bevy::window::CompositeAlphaMode::Inherit, false),
+            CompositeAlpha::Inherit
+        );
+        // Unsupported, selected automatically instead
+        assert_eq!(
+            support.select_composite_alpha(bevy::window::CompositeAlphaMode::PreMultiplied, true),
+            CompositeAlpha::Inherit
+        );
+    }
+
+    #[test]
+    fn composite_alpha_falls_back_to_first_supported() {
+        let mut support = surface_support();
+        support.composite_alpha = vec![CompositeAlpha::PostMultiplied];
+        assert_eq!(
+            support.select_composite_alpha(bevy::window::CompositeAlphaMode::Auto, false),
+            CompositeAlpha::PostMultiplied
+        );
+    }
+
+    #[test]
+    fn image_count_is_clamped() {
+        let support = surface_support();
+        assert_eq!(support.clamp_image_count(1), 2);
+        assert_eq!(support.clamp_image_count(3), 3);
+        assert_eq!(support.clamp_image_count(8), 4);
+    }
+
+    #[test]
+    fn image_count_without_max() {
+        let mut support = surface_support();
+        support.max_image_count = None;
+        assert_eq!(support.clamp_image_count(8), 8);
+        support.max_image_count = Some(0);
+        assert_eq!(support.clamp_image_count(8), 8);
+        assert_eq!(support.clamp_image_count(0), 2);
+    }
+}
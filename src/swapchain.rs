@@ -0,0 +1,151 @@
+use std::{cell::Cell, sync::Arc};
+
+use bevy::{log::error, prelude::Component};
+use vulkano::{
+    format::Format,
+    image::ImageUsage,
+    swapchain::{ColorSpace, CompositeAlpha, PresentMode, SwapchainCreateInfo},
+    sync::{future::FenceSignalFuture, GpuFuture},
+};
+use vulkano_util::{
+    context::VulkanoContext, renderer::VulkanoWindowRenderer,
+    window::WindowDescriptor as VulkanoWindowDescriptor,
+};
+
+/// Swapchain settings of a window. Insert it on the window entity before the window is created,
+/// changes afterwards have no effect.
+#[derive(Debug, Clone, Component)]
+pub struct SwapchainConfig {
+    /// Minimum number of swapchain images, clamped to what the surface supports. `None` uses the
+    /// surface's minimum.
+    pub image_count: Option<u32>,
+    /// How many frames may be in flight on the GPU at once. Use
+    /// [`BevyVulkanoWindows::frame_in_flight`](crate::BevyVulkanoWindows::frame_in_flight) to index
+    /// per frame resources, such as uniform buffers.
+    pub frames_in_flight: usize,
+}
+
+impl Default for SwapchainConfig {
+    fn default() -> Self {
+        SwapchainConfig {
+            image_count: None,
+            frames_in_flight: 2,
+        }
+    }
+}
+
+/// Current swapchain of a window, so systems can read render target info without borrowing
+/// [`BevyVulkanoWindows`](crate::BevyVulkanoWindows). Updated at the start of each frame, so it
+/// reflects the swapchain as of the last frame's acquire.
+#[derive(Debug, Clone, PartialEq, Component)]
+pub struct SwapchainInfo {
+    pub extent: [u32; 2],
+    pub format: Format,
+    pub color_space: ColorSpace,
+    pub present_mode: PresentMode,
+    pub composite_alpha: CompositeAlpha,
+    pub image_count: u32,
+    /// Index of the last acquired swapchain image
+    pub image_index: u32,
+}
+
+impl SwapchainInfo {
+    pub(crate) fn from_renderer(window_renderer: &VulkanoWindowRenderer) -> SwapchainInfo {
+        let image_view = window_renderer.swapchain_image_view();
+        let swapchain = image_view.image().swapchain();
+        SwapchainInfo {
+            extent: swapchain.image_extent(),
+            format: swapchain.image_format(),
+            color_space: swapchain.image_color_space(),
+            present_mode: swapchain.present_mode(),
+            composite_alpha: swapchain.composite_alpha(),
+            image_count: swapchain.image_count(),
+            image_index: window_renderer.image_index(),
+        }
+    }
+}
+
+/// Settings applied by `modify_swapchain_create_info`, already validated against the surface
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SwapchainCreateSettings {
+    pub min_image_count: u32,
+    pub composite_alpha: CompositeAlpha,
+    pub image_usage: ImageUsage,
+}
+
+thread_local! {
+    // `VulkanoWindowRenderer::new` takes a function pointer, so per window settings can't be
+    // captured and are passed through here instead
+    static SWAPCHAIN_CREATE_SETTINGS: Cell<Option<SwapchainCreateSettings>> = const { Cell::new(None) };
+}
+
+fn modify_swapchain_create_info(create_info: &mut SwapchainCreateInfo) {
+    create_info.image_format = Some(Format::B8G8R8A8_SRGB);
+    if let Some(settings) = SWAPCHAIN_CREATE_SETTINGS.with(Cell::get) {
+        create_info.min_image_count = settings.min_image_count;
+        create_info.composite_alpha = settings.composite_alpha;
+        create_info.image_usage = settings.image_usage;
+    }
+}
+
+/// Creates the window renderer, with its swapchain created using `settings`
+pub(crate) fn create_window_renderer(
+    vulkano_context: &VulkanoContext,
+    winit_window: winit::window::Window,
+    descriptor: &VulkanoWindowDescriptor,
+    settings: SwapchainCreateSettings,
+) -> VulkanoWindowRenderer {
+    SWAPCHAIN_CREATE_SETTINGS.with(|s| s.set(Some(settings)));
+    let window_renderer = VulkanoWindowRenderer::new(
+        vulkano_context,
+        winit_window,
+        descriptor,
+        modify_swapchain_create_info,
+    );
+    SWAPCHAIN_CREATE_SETTINGS.with(|s| s.set(None));
+    window_renderer
+}
+
+/// Fences of the frames in flight of a window. Before a frame is started, waits for the GPU to
+/// finish the frame that last used the same index, so its resources can be reused.
+pub(crate) struct FramesInFlight {
+    fences: Vec<Option<Arc<FenceSignalFuture<Box<dyn GpuFuture>>>>>,
+    index: usize,
+}
+
+impl FramesInFlight {
+    pub(crate) fn new(frames_in_flight: usize) -> FramesInFlight {
+        FramesInFlight {
+            fences: (0..frames_in_flight.max(1)).map(|_| None).collect(),
+            index: 0,
+        }
+    }
+
+    pub(crate) fn index(&self) -> usize {
+        self.index
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.fences.len()
+    }
+
+    /// Moves to the next frame, blocking until the GPU has finished its previous use
+    pub(crate) fn advance(&mut self) {
+        self.index = (self.index + 1) % self.fences.len();
+        if let Some(fence) = self.fences[self.index].take() {
+            if let Err(err) = fence.wait(None) {
+                error!("Failed to wait for frame in flight: {}", err);
+            }
+        }
+    }
+
+    /// Signals the current frame's fence once `future` has finished. The returned future signals a
+    /// semaphore after the fence, so that presenting waits for `future` as well.
+    // Vulkano only implements `GpuFuture` for `Arc` wrapped fence futures, `Rc` can't be chained
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub(crate) fn signal(&mut self, future: Box<dyn GpuFuture>) -> Box<dyn GpuFuture> {
+        let fence = Arc::new(future.then_signal_fence());
+        self.fences[self.index] = Some(fence.clone());
+        fence.then_signal_semaphore().boxed()
+    }
+}
@@ -0,0 +1,160 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::{
+    log::{debug, error, info, warn},
+    prelude::{Res, Resource},
+};
+use vulkano::{
+    instance::debug::{
+        DebugUtilsMessageSeverity, DebugUtilsMessageType, DebugUtilsMessengerCreateInfo, Message,
+    },
+    VulkanLibrary,
+};
+use vulkano_util::context::VulkanoConfig;
+
+const VALIDATION_LAYER: &str = "VK_LAYER_KHRONOS_validation";
+
+/// Forwards Vulkan debug messages, such as validation errors, to Bevy's log, see
+/// [`VulkanoContextPlugin::with_validation_logging`](crate::VulkanoContextPlugin::with_validation_logging).
+/// Errors are logged with `error!`, warnings with `warn!`, info with `info!` and verbose messages
+/// with `debug!`.
+#[derive(Debug, Clone)]
+pub struct ValidationLogging {
+    /// Severities to forward
+    pub message_severity: DebugUtilsMessageSeverity,
+    /// Message types to forward, e.g. [`DebugUtilsMessageType::PERFORMANCE`] for performance
+    /// warnings
+    pub message_type: DebugUtilsMessageType,
+    /// Enables the Khronos validation layer if it's installed
+    pub enable_validation_layer: bool,
+    /// Panics at the end of the frame in which an error message was received, e.g. to fail tests.
+    /// Panicking in the callback itself isn't possible, vulkano catches it.
+    pub panic_on_error: bool,
+    /// Message ID names not to forward, e.g. `VUID-vkCmdDraw-None-02699`
+    pub suppressed_message_ids: Vec<String>,
+}
+
+impl Default for ValidationLogging {
+    fn default() -> Self {
+        ValidationLogging {
+            message_severity: DebugUtilsMessageSeverity::ERROR | DebugUtilsMessageSeverity::WARNING,
+            message_type: DebugUtilsMessageType::GENERAL
+                | DebugUtilsMessageType::VALIDATION
+                | DebugUtilsMessageType::PERFORMANCE,
+            enable_validation_layer: true,
+            panic_on_error: false,
+            suppressed_message_ids: Vec::new(),
+        }
+    }
+}
+
+impl ValidationLogging {
+    /// Adds the validation layer, debug utils extension and messenger to the config, skipping
+    /// what isn't supported. Returns the errors received by the messenger.
+    pub(crate) fn apply(&self, config: &mut VulkanoConfig) -> ValidationErrors {
+        let errors = ValidationErrors::default();
+        let library = match VulkanLibrary::new() {
+            Ok(library) => library,
+            // Creating the context will fail with a better error
+            Err(_) => return errors,
+        };
+        if self.enable_validation_layer {
+            let available = library.layer_properties().map_or(false, |mut layers| {
+                layers.any(|layer| layer.name() == VALIDATION_LAYER)
+            });
+            if available {
+                if !config
+                    .instance_create_info
+                    .enabled_layers
+                    .iter()
+                    .any(|layer| layer == VALIDATION_LAYER)
+                {
+                    config
+                        .instance_create_info
+                        .enabled_layers
+                        .push(VALIDATION_LAYER.to_string());
+                }
+            } else {
+                warn!(
+                    "{} isn't installed, validation is disabled",
+                    VALIDATION_LAYER
+                );
+            }
+        }
+        // Layers, such as the validation layer, may provide the extension
+        let debug_utils_supported = library
+            .supported_extensions_with_layers(
+                config
+                    .instance_create_info
+                    .enabled_layers
+                    .iter()
+                    .map(String::as_str),
+            )
+            .map_or(false, |extensions| extensions.ext_debug_utils);
+        if !debug_utils_supported {
+            warn!("VK_EXT_debug_utils isn't supported, Vulkan messages aren't logged");
+            return errors;
+        }
+        if config.debug_create_info.is_some() {
+            warn!("Replacing the config's debug callback with validation logging");
+        }
+        config
+            .instance_create_info
+            .enabled_extensions
+            .ext_debug_utils = true;
+
+        let recorded_errors = errors.0.clone();
+        let panic_on_error = self.panic_on_error;
+        let suppressed_message_ids = self.suppressed_message_ids.clone();
+        config.debug_create_info = Some(DebugUtilsMessengerCreateInfo {
+            message_severity: self.message_severity,
+            message_type: self.message_type,
+            ..DebugUtilsMessengerCreateInfo::user_callback(Arc::new(move |message: &Message| {
+                let message_id = message.layer_prefix.unwrap_or("unknown");
+                if suppressed_message_ids.iter().any(|id| id == message_id) {
+                    return;
+                }
+                let ty = if message.ty.intersects(DebugUtilsMessageType::VALIDATION) {
+                    "validation"
+                } else if message.ty.intersects(DebugUtilsMessageType::PERFORMANCE) {
+                    "performance"
+                } else {
+                    "general"
+                };
+                if message
+                    .severity
+                    .intersects(DebugUtilsMessageSeverity::ERROR)
+                {
+                    error!("Vulkan {} [{}]: {}", ty, message_id, message.description);
+                    if panic_on_error {
+                        recorded_errors
+                            .lock()
+                            .unwrap()
+                            .push(format!("[{}]: {}", message_id, message.description));
+                    }
+                } else if message
+                    .severity
+                    .intersects(DebugUtilsMessageSeverity::WARNING)
+                {
+                    warn!("Vulkan {} [{}]: {}", ty, message_id, message.description);
+                } else if message.severity.intersects(DebugUtilsMessageSeverity::INFO) {
+                    info!("Vulkan {} [{}]: {}", ty, message_id, message.description);
+                } else {
+                    debug!("Vulkan {} [{}]: {}", ty, message_id, message.description);
+                }
+            }))
+        });
+        errors
+    }
+}
+
+/// Vulkan errors recorded when [`ValidationLogging::panic_on_error`] is set
+#[derive(Debug, Default, Clone, Resource)]
+pub(crate) struct ValidationErrors(Arc<Mutex<Vec<String>>>);
+
+pub(crate) fn panic_on_validation_error_system(errors: Res<ValidationErrors>) {
+    let errors = errors.0.lock().unwrap();
+    if !errors.is_empty() {
+        panic!("Vulkan validation errors:\n{}", errors.join("\n"));
+    }
+}
@@ -1,19 +1,29 @@
 #![allow(clippy::field_reassign_with_default)]
 
+use std::sync::Arc;
+
 use bevy::{
     math::IVec2,
-    prelude::{Commands, Entity},
+    prelude::{Commands, Component, Entity},
     utils::{
         hashbrown::hash_map::{Iter, IterMut},
-        HashMap,
-    },
-    window::{
-        MonitorSelection, PresentMode, RawHandleWrapper, Window, WindowMode, WindowResolution,
+        HashMap, HashSet,
     },
+    window::{MonitorSelection, RawHandleWrapper, Window, WindowMode},
 };
 #[cfg(feature = "gui")]
 use egui_winit_vulkano::{Gui, GuiConfig};
 use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
+use vulkano::{
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator, AutoCommandBufferBuilder, ClearColorImageInfo,
+        CommandBufferUsage,
+    },
+    format::ClearColorValue,
+    image::ImageUsage,
+    swapchain::AcquireError,
+    sync::GpuFuture,
+};
 use vulkano_util::{
     context::VulkanoContext,
     renderer::VulkanoWindowRenderer,
@@ -22,16 +32,25 @@ use vulkano_util::{
         WindowResizeConstraints as VulkanoWindowResizeConstraints,
     },
 };
+use vulkano_win::create_surface_from_winit;
 use winit::{
     dpi::{LogicalPosition, LogicalSize, PhysicalPosition},
     window::{CursorGrabMode, WindowId},
 };
 
-use crate::VulkanoWinitConfig;
+use crate::{
+    converters,
+    input::HeldInputs,
+    surface::negotiate_present_mode,
+    swapchain::{create_window_renderer, FramesInFlight, SwapchainCreateSettings, SwapchainInfo},
+    EffectivePresentMode, KeyboardModifiers, SurfaceSupport, SwapchainConfig, VulkanoWinitConfig,
+    WindowState, WindowVisible,
+};
 
 fn window_descriptor_to_vulkano_window_descriptor(
     wd: &Window,
     position: Option<[f32; 2]>,
+    present_mode: vulkano::swapchain::PresentMode,
 ) -> VulkanoWindowDescriptor {
     let mut window_descriptor = VulkanoWindowDescriptor::default();
     window_descriptor.width = wd.width();
@@ -45,13 +64,8 @@ fn window_descriptor_to_vulkano_window_descriptor(
     };
     window_descriptor.scale_factor_override = wd.resolution.scale_factor_override();
     window_descriptor.title = wd.title.clone();
-    window_descriptor.present_mode = match wd.present_mode {
-        PresentMode::Fifo => vulkano::swapchain::PresentMode::Fifo,
-        PresentMode::Immediate => vulkano::swapchain::PresentMode::Immediate,
-        PresentMode::Mailbox => vulkano::swapchain::PresentMode::Mailbox,
-        PresentMode::AutoNoVsync => vulkano::swapchain::PresentMode::Immediate,
-        PresentMode::AutoVsync => vulkano::swapchain::PresentMode::FifoRelaxed,
-    };
+    // Negotiated with the surface from `wd.present_mode`, see `negotiate_present_mode`
+    window_descriptor.present_mode = present_mode;
     window_descriptor.resizable = wd.resizable;
     window_descriptor.decorations = wd.decorations;
     window_descriptor.cursor_visible = wd.cursor.visible;
@@ -69,6 +83,12 @@ fn window_descriptor_to_vulkano_window_descriptor(
     window_descriptor
 }
 
+/// The cached state of the window so we can check which properties were changed from within the app.
+#[derive(Debug, Clone, Component)]
+pub struct CachedWindow {
+    pub window: Window,
+}
+
 #[derive(Default)]
 pub struct BevyVulkanoWindows {
     #[cfg(not(feature = "gui"))]
@@ -79,17 +99,29 @@ pub struct BevyVulkanoWindows {
     pub(crate) entity_to_winit: HashMap<Entity, winit::window::WindowId>,
     /// Maps `winit` window identifiers to entities.
     pub(crate) winit_to_entity: HashMap<winit::window::WindowId, Entity>,
+    /// Keys and buttons held down per window, released when the window loses focus.
+    pub(crate) held_inputs: HashMap<Entity, HeldInputs>,
+    /// Windows that are fully hidden from view and shouldn't be rendered to.
+    pub(crate) occluded: HashSet<Entity>,
+    /// Windows with a zero sized inner area, whose swapchains can't be recreated.
+    pub(crate) minimized: HashSet<Entity>,
+    /// Fences of the frames in flight per window, see [`SwapchainConfig::frames_in_flight`].
+    pub(crate) frames_in_flight: HashMap<Entity, FramesInFlight>,
+    /// Allocator for [`clear`](Self::clear) command buffers, created on first use.
+    command_buffer_allocator: Option<StandardCommandBufferAllocator>,
 }
 
 impl BevyVulkanoWindows {
-    pub fn create_window(
+    pub fn create_window<T>(
         &mut self,
         commands: &mut Commands,
-        event_loop: &winit::event_loop::EventLoopWindowTarget<()>,
+        event_loop: &winit::event_loop::EventLoopWindowTarget<T>,
         window_entity: Entity,
         window: &Window,
         vulkano_context: &VulkanoContext,
         _config: &VulkanoWinitConfig,
+        swapchain_config: Option<&SwapchainConfig>,
+        visible: Option<WindowVisible>,
     ) -> Window {
         #[cfg(target_os = "windows")]
         let mut winit_window_builder = {
@@ -157,16 +189,9 @@ impl BevyVulkanoWindows {
                         }
                     }
                      bevy::window::WindowPosition::At(position) => {
-                        if let Some(sf) = scale_factor_override {
-                            winit_window_builder = winit_window_builder.with_position(
-                                LogicalPosition::new(position[0] as f64, position[1] as f64)
-                                    .to_physical::<f64>(sf),
-                            );
-                        } else {
-                            winit_window_builder = winit_window_builder.with_position(
-                                LogicalPosition::new(position[0] as f64, position[1] as f64),
-                            );
-                        }
+                        // Physical pixels, like the positions of window moved events
+                        winit_window_builder = winit_window_builder
+                            .with_position(PhysicalPosition::new(position[0], position[1]));
                     }
                 }
 
@@ -181,9 +206,12 @@ impl BevyVulkanoWindows {
             }
             .with_resizable(window.resizable)
             .with_decorations(window.decorations)
-            .with_transparent(window.transparent),
+            .with_transparent(window.transparent)
+            .with_window_level(converters::convert_window_level(window.window_level)),
         };
 
+        winit_window_builder = winit_window_builder.with_visible(visible.unwrap_or_default().0);
+
         let constraints = window.resize_constraints.check_constraints();
         let min_inner_size = LogicalSize {
             width: constraints.min_width,
@@ -208,17 +236,22 @@ impl BevyVulkanoWindows {
 
         let winit_window = winit_window_builder.build(event_loop).unwrap();
 
-        match winit_window.set_cursor_grab(match window.cursor.grab_mode {
-            bevy::window::CursorGrabMode::Locked => CursorGrabMode::Locked,
-            bevy::window::CursorGrabMode::Confined => CursorGrabMode::Confined,
-            bevy::window::CursorGrabMode::None => CursorGrabMode::None,
-        }) {
-            Ok(_) => {}
-            Err(winit::error::ExternalError::NotSupported(_)) => {}
-            Err(err) => Err(err).unwrap(),
-        }
+        let grab_mode = match attempt_grab(&winit_window, window.cursor.grab_mode) {
+            Ok(grab_mode) => grab_mode,
+            Err(err) => {
+                bevy::log::warn!("Unable to grab cursor: {}", err);
+                bevy::window::CursorGrabMode::None
+            }
+        };
 
         winit_window.set_cursor_visible(window.cursor.visible);
+        winit_window.set_cursor_icon(converters::convert_cursor_icon(window.cursor.icon));
+
+        winit_window.set_ime_allowed(window.ime_enabled);
+        winit_window.set_ime_position(LogicalPosition::new(
+            window.ime_position.x,
+            window.ime_position.y,
+        ));
 
         let winit_id = winit_window.id();
         self.entity_to_winit
@@ -238,25 +271,82 @@ impl BevyVulkanoWindows {
             display_handle: winit_window.raw_display_handle(),
         };
 
-        // TODO Do we need bevy winit for CacheWindow?
+        // The window as created by the backend
+        let mut resolution = window.resolution.clone();
+        resolution.set_scale_factor(scale_factor);
+        resolution.set_physical_resolution(inner_size.width, inner_size.height);
+        let mut created_window = Window {
+            position: position.map_or(window.position, bevy::window::WindowPosition::At),
+            resolution,
+            ..window.clone()
+        };
+        created_window.cursor.grab_mode = grab_mode;
+
         commands
             .entity(window_entity)
-            .insert(raw_window_handle_wrapper);
-        // .insert(CachedWindow {
-        //     window: window.clone(),
-        // });
+            .insert(raw_window_handle_wrapper)
+            .insert(CachedWindow {
+                window: created_window.clone(),
+            })
+            .insert(KeyboardModifiers::default())
+            .insert(WindowState {
+                theme: winit_window.theme().map(converters::convert_theme),
+                occluded: false,
+                minimized: inner_size.width == 0 || inner_size.height == 0,
+                maximized: winit_window.is_maximized(),
+            });
+        if visible.is_none() {
+            commands
+                .entity(window_entity)
+                .insert(WindowVisible::default());
+        }
+        if inner_size.width == 0 || inner_size.height == 0 {
+            self.minimized.insert(window_entity);
+        }
 
-        let window_renderer = VulkanoWindowRenderer::new(
+        // Query what the surface supports with a temporary surface, so the swapchain is created
+        // with supported settings
+        let winit_window = Arc::new(winit_window);
+        let surface_support = SurfaceSupport::query(
+            vulkano_context.device().physical_device(),
+            &create_surface_from_winit(winit_window.clone(), vulkano_context.instance().clone())
+                .unwrap(),
+        )
+        .unwrap();
+        let winit_window = Arc::try_unwrap(winit_window).unwrap();
+
+        let swapchain_config = swapchain_config.cloned().unwrap_or_default();
+        let present_mode = negotiate_present_mode(&surface_support, window.present_mode);
+        let window_renderer = create_window_renderer(
             vulkano_context,
             winit_window,
             &window_descriptor_to_vulkano_window_descriptor(
                 window,
                 position.map(|p| [p.x as f32, p.y as f32]),
+                present_mode,
             ),
-            move |ci| {
-                ci.image_format = Some(vulkano::format::Format::B8G8R8A8_SRGB);
+            SwapchainCreateSettings {
+                min_image_count: surface_support.clamp_image_count(
+                    swapchain_config
+                        .image_count
+                        .unwrap_or(surface_support.min_image_count),
+                ),
+                composite_alpha: surface_support
+                    .select_composite_alpha(window.composite_alpha_mode, window.transparent),
+                // Transfer destination allows clearing the images, see `BevyVulkanoWindows::clear`
+                image_usage: ImageUsage::COLOR_ATTACHMENT
+                    | (surface_support.image_usage & ImageUsage::TRANSFER_DST),
             },
         );
+        self.frames_in_flight.insert(
+            window_entity,
+            FramesInFlight::new(swapchain_config.frames_in_flight),
+        );
+        commands
+            .entity(window_entity)
+            .insert(surface_support)
+            .insert(EffectivePresentMode(present_mode))
+            .insert(SwapchainInfo::from_renderer(&window_renderer));
 
         #[cfg(feature = "gui")]
         {
@@ -276,12 +366,7 @@ impl BevyVulkanoWindows {
         #[cfg(not(feature = "gui"))]
         self.windows.insert(winit_id, window_renderer);
 
-        Window {
-            position: bevy::window::WindowPosition::At(position.unwrap()),
-            resolution: WindowResolution::new(inner_size.width as f32, inner_size.height as f32)
-                .with_scale_factor_override(scale_factor),
-            ..window.clone()
-        }
+        created_window
     }
 
     #[cfg(not(feature = "gui"))]
@@ -348,6 +433,164 @@ impl BevyVulkanoWindows {
         self.winit_to_entity.get(&window_id).cloned()
     }
 
+    /// Whether the window is fully hidden from view, see [`WindowState::occluded`].
+    pub fn is_occluded(&self, entity: Entity) -> bool {
+        self.occluded.contains(&entity)
+    }
+
+    /// Whether the window is minimized, see [`WindowState::minimized`].
+    pub fn is_minimized(&self, entity: Entity) -> bool {
+        self.minimized.contains(&entity)
+    }
+
+    /// Whether the window can be rendered to, see [`WindowState::is_presentable`].
+    pub fn is_presentable(&self, entity: Entity) -> bool {
+        !self.is_occluded(entity) && !self.is_minimized(entity)
+    }
+
+    /// Queries what the window's surface currently supports. Returns `None` for unknown windows, and
+    /// logs an error and returns `None` if the surface or device was lost.
+    pub fn surface_support(&self, entity: Entity) -> Option<SurfaceSupport> {
+        let winit_id = self.entity_to_winit.get(&entity)?;
+        #[cfg(not(feature = "gui"))]
+        let window_renderer = self.windows.get(winit_id)?;
+        #[cfg(feature = "gui")]
+        let (window_renderer, _) = self.windows.get(winit_id)?;
+        let surface = window_renderer.surface();
+        SurfaceSupport::query(
+            window_renderer.graphics_queue().device().physical_device(),
+            &surface,
+        )
+        .map_err(|err| bevy::log::error!("Failed to query surface support: {}", err))
+        .ok()
+    }
+
+    /// Current swapchain info of the window, see [`SwapchainInfo`]. Returns `None` for unknown
+    /// windows.
+    pub fn swapchain_info(&self, entity: Entity) -> Option<SwapchainInfo> {
+        let winit_id = self.entity_to_winit.get(&entity)?;
+        #[cfg(not(feature = "gui"))]
+        let window_renderer = self.windows.get(winit_id)?;
+        #[cfg(feature = "gui")]
+        let (window_renderer, _) = self.windows.get(winit_id)?;
+        Some(SwapchainInfo::from_renderer(window_renderer))
+    }
+
+    /// Starts a frame by acquiring the next swapchain image of the window, see
+    /// [`VulkanoWindowRenderer::acquire`]. Returns `None` for unknown windows and for occluded or
+    /// minimized windows, which should be skipped this frame.
+    pub fn acquire(&mut self, entity: Entity) -> Option<Result<Box<dyn GpuFuture>, AcquireError>> {
+        if !self.is_presentable(entity) {
+            return None;
+        }
+        let winit_id = self.entity_to_winit.get(&entity)?;
+        #[cfg(not(feature = "gui"))]
+        let window_renderer = self.windows.get_mut(winit_id)?;
+        #[cfg(feature = "gui")]
+        let (window_renderer, _) = self.windows.get_mut(winit_id)?;
+        let frames_in_flight = self.frames_in_flight.get_mut(&entity)?;
+        frames_in_flight.advance();
+        Some(window_renderer.acquire())
+    }
+
+    /// Finishes a frame started with [`acquire`](Self::acquire) by presenting the swapchain image
+    /// after `after_future`, see [`VulkanoWindowRenderer::present`]. Presenting through here lets
+    /// the next frames wait for this one, so that frame in flight resources can be reused safely.
+    pub fn present(&mut self, entity: Entity, after_future: Box<dyn GpuFuture>, wait_future: bool) {
+        let (winit_id, frames_in_flight) = match (
+            self.entity_to_winit.get(&entity),
+            self.frames_in_flight.get_mut(&entity),
+        ) {
+            (Some(winit_id), Some(frames_in_flight)) => (winit_id, frames_in_flight),
+            _ => return,
+        };
+        #[cfg(not(feature = "gui"))]
+        let window_renderer = self.windows.get_mut(winit_id);
+        #[cfg(feature = "gui")]
+        let window_renderer = self
+            .windows
+            .get_mut(winit_id)
+            .map(|(window_renderer, _)| window_renderer);
+        if let Some(window_renderer) = window_renderer {
+            window_renderer.present(frames_in_flight.signal(after_future), wait_future);
+        }
+    }
+
+    /// Clears the window's current swapchain image to `color` after `before_future`, e.g. to
+    /// `[0.0; 4]` for transparent windows. Call between [`acquire`](Self::acquire) and
+    /// [`present`](Self::present). If the swapchain uses `PreMultiplied` composite alpha, the color
+    /// must be premultiplied as well.
+    pub fn clear(
+        &mut self,
+        entity: Entity,
+        before_future: Box<dyn GpuFuture>,
+        color: [f32; 4],
+    ) -> Box<dyn GpuFuture> {
+        let winit_id = match self.entity_to_winit.get(&entity) {
+            Some(winit_id) => winit_id,
+            None => return before_future,
+        };
+        #[cfg(not(feature = "gui"))]
+        let window_renderer = self.windows.get(winit_id);
+        #[cfg(feature = "gui")]
+        let window_renderer = self
+            .windows
+            .get(winit_id)
+            .map(|(window_renderer, _)| window_renderer);
+        let window_renderer = match window_renderer {
+            Some(window_renderer) => window_renderer,
+            None => return before_future,
+        };
+
+        let image = window_renderer.swapchain_image_view().image().clone();
+        if !image
+            .swapchain()
+            .image_usage()
+            .intersects(ImageUsage::TRANSFER_DST)
+        {
+            bevy::log::error!("Swapchain images of {:?} don't support clearing", entity);
+            return before_future;
+        }
+        let queue = window_renderer.graphics_queue();
+        let command_buffer_allocator = self.command_buffer_allocator.get_or_insert_with(|| {
+            StandardCommandBufferAllocator::new(queue.device().clone(), Default::default())
+        });
+        let mut builder = AutoCommandBufferBuilder::primary(
+            command_buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        builder
+            .clear_color_image(ClearColorImageInfo {
+                clear_value: ClearColorValue::Float(color),
+                ..ClearColorImageInfo::image(image)
+            })
+            .unwrap();
+        let command_buffer = builder.build().unwrap();
+        before_future
+            .then_execute(queue, command_buffer)
+            .unwrap()
+            .boxed()
+    }
+
+    /// Index of the current frame in flight of the window, in `0..frames_in_flight`. Resources
+    /// indexed by it are no longer in use by the GPU once [`acquire`](Self::acquire) returns, if
+    /// frames are presented with [`present`](Self::present).
+    pub fn frame_in_flight(&self, entity: Entity) -> Option<usize> {
+        self.frames_in_flight
+            .get(&entity)
+            .map(|frames_in_flight| frames_in_flight.index())
+    }
+
+    /// Number of frames that may be in flight for the window, see
+    /// [`SwapchainConfig::frames_in_flight`].
+    pub fn frames_in_flight(&self, entity: Entity) -> Option<usize> {
+        self.frames_in_flight
+            .get(&entity)
+            .map(|frames_in_flight| frames_in_flight.len())
+    }
+
     #[cfg(not(feature = "gui"))]
     pub fn iter(&self) -> Iter<winit::window::WindowId, VulkanoWindowRenderer> {
         self.windows.iter()
@@ -369,6 +612,36 @@ impl BevyVulkanoWindows {
     }
 }
 
+/// Grabs the cursor with the requested mode. If the platform doesn't support it, falls back to the
+/// other grab mode, e.g. `Locked` to `Confined` on Windows. Returns the grab mode that was applied.
+pub(crate) fn attempt_grab(
+    winit_window: &winit::window::Window,
+    grab_mode: bevy::window::CursorGrabMode,
+) -> Result<bevy::window::CursorGrabMode, winit::error::ExternalError> {
+    use bevy::window::CursorGrabMode as BevyCursorGrabMode;
+    match grab_mode {
+        BevyCursorGrabMode::None => winit_window
+            .set_cursor_grab(CursorGrabMode::None)
+            .map(|_| BevyCursorGrabMode::None),
+        BevyCursorGrabMode::Confined => winit_window
+            .set_cursor_grab(CursorGrabMode::Confined)
+            .map(|_| BevyCursorGrabMode::Confined)
+            .or_else(|_| {
+                winit_window
+                    .set_cursor_grab(CursorGrabMode::Locked)
+                    .map(|_| BevyCursorGrabMode::Locked)
+            }),
+        BevyCursorGrabMode::Locked => winit_window
+            .set_cursor_grab(CursorGrabMode::Locked)
+            .map(|_| BevyCursorGrabMode::Locked)
+            .or_else(|_| {
+                winit_window
+                    .set_cursor_grab(CursorGrabMode::Confined)
+                    .map(|_| BevyCursorGrabMode::Confined)
+            }),
+    }
+}
+
 pub fn get_fitting_videomode(
     monitor: &winit::monitor::MonitorHandle,
     width: u32,
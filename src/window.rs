@@ -0,0 +1,168 @@
+use std::path::PathBuf;
+
+use bevy::{
+    prelude::{Component, Entity, Query, With},
+    window::{CursorGrabMode, PrimaryWindow},
+};
+
+/// Light or dark OS theme of a window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowTheme {
+    Light,
+    Dark,
+}
+
+/// Window state reported by the OS that [`Window`](bevy::window::Window) doesn't carry. Inserted
+/// on window entities when the window is created and kept up to date by the runner.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Component)]
+pub struct WindowState {
+    /// Current OS theme of the window. `None` if the platform doesn't report one.
+    pub theme: Option<WindowTheme>,
+    /// Whether the window is fully hidden from view, e.g. covered by other windows. Swapchain
+    /// images aren't acquired for occluded windows through
+    /// [`BevyVulkanoWindows::acquire`](crate::BevyVulkanoWindows::acquire).
+    pub occluded: bool,
+    /// Whether the window has a zero sized inner area, e.g. because it is minimized. The swapchain
+    /// isn't recreated while the window is minimized.
+    pub minimized: bool,
+    /// Whether the window is maximized. Request changes with
+    /// [`Window::set_maximized`](bevy::window::Window::set_maximized).
+    pub maximized: bool,
+}
+
+impl WindowState {
+    /// Whether the window can be rendered to, i.e. it's neither occluded nor minimized
+    pub fn is_presentable(&self) -> bool {
+        !self.occluded && !self.minimized
+    }
+}
+
+/// Run condition that is true while the primary window can be rendered to. Add it to your render
+/// systems to pause them while the window is minimized or occluded.
+pub fn primary_window_presentable(windows: Query<&WindowState, With<PrimaryWindow>>) -> bool {
+    windows
+        .get_single()
+        .map_or(false, |state| state.is_presentable())
+}
+
+/// Run condition that is true while any window can be rendered to.
+pub fn any_window_presentable(windows: Query<&WindowState>) -> bool {
+    windows.iter().any(|state| state.is_presentable())
+}
+
+/// An event that is sent when the OS theme of a window changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowThemeChanged {
+    pub window: Entity,
+    pub theme: WindowTheme,
+}
+
+/// An event that is sent when a window becomes fully hidden from view or visible again.
+///
+/// Not reported on all platforms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowOccluded {
+    pub window: Entity,
+    pub occluded: bool,
+}
+
+/// An event that is sent when the OS has destroyed a window, after it has been closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowDestroyed {
+    pub window: Entity,
+}
+
+/// An event that is sent when the cursor grab mode requested through
+/// [`Window::cursor`](bevy::window::Window::cursor) couldn't be applied. The window's grab mode is
+/// set back to the mode that is in effect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CursorGrabFailed {
+    pub window: Entity,
+    /// Grab mode that was requested
+    pub requested: CursorGrabMode,
+    /// Grab mode in effect, the fallback mode if the platform supports it
+    pub applied: CursorGrabMode,
+}
+
+/// Icon of a window, shown e.g. in the title bar and taskbar. Add this to a window entity to set
+/// its icon. Changing the component updates the icon, removing it clears the icon.
+#[derive(Debug, Clone, PartialEq, Eq, Component)]
+pub enum WindowIcon {
+    /// Image file decoded with the `image` crate
+    Path(PathBuf),
+    /// RGBA8 pixels, row by row
+    Rgba {
+        rgba: Vec<u8>,
+        width: u32,
+        height: u32,
+    },
+}
+
+impl WindowIcon {
+    pub fn from_path(path: impl Into<PathBuf>) -> Self {
+        WindowIcon::Path(path.into())
+    }
+
+    pub fn from_rgba(rgba: Vec<u8>, width: u32, height: u32) -> Self {
+        WindowIcon::Rgba {
+            rgba,
+            width,
+            height,
+        }
+    }
+
+    /// Decodes the icon for winit. Returns `None` and logs an error if the icon is invalid.
+    pub(crate) fn to_winit_icon(&self) -> Option<winit::window::Icon> {
+        let icon = match self {
+            WindowIcon::Path(path) => match image::open(path) {
+                Ok(image) => {
+                    let image = image.into_rgba8();
+                    let (width, height) = image.dimensions();
+                    winit::window::Icon::from_rgba(image.into_raw(), width, height)
+                }
+                Err(err) => {
+                    bevy::log::error!("Failed to load window icon {:?}: {}", path, err);
+                    return None;
+                }
+            },
+            WindowIcon::Rgba {
+                rgba,
+                width,
+                height,
+            } => winit::window::Icon::from_rgba(rgba.clone(), *width, *height),
+        };
+        icon.map_err(|err| bevy::log::error!("Invalid window icon: {}", err))
+            .ok()
+    }
+}
+
+/// Whether a window is shown. Inserted on window entities when the window is created, change it to
+/// hide or show the window. Insert `WindowVisible(false)` before the window is created to start it
+/// hidden. The primary window is configured through
+/// [`VulkanoWinitConfig::primary_window_visible`](crate::VulkanoWinitConfig::primary_window_visible).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+pub struct WindowVisible(pub bool);
+
+impl Default for WindowVisible {
+    fn default() -> Self {
+        WindowVisible(true)
+    }
+}
+
+/// Kind of user attention to request, see [`RequestUserAttention`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserAttention {
+    /// Until the application is in focus, e.g. bounces the dock icon on macOS and flashes the
+    /// taskbar on Windows until the window is focused
+    Critical,
+    /// A single flash or bounce of the taskbar or dock icon
+    Informational,
+}
+
+/// Send this event to request the user's attention to a window, e.g. when a match has been found.
+/// `None` cancels a previous request. Requests for focused windows are ignored by the OS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestUserAttention {
+    pub window: Entity,
+    pub attention: Option<UserAttention>,
+}
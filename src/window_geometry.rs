@@ -0,0 +1,383 @@
+use std::{fs, path::PathBuf};
+
+use bevy::{
+    app::{App, AppExit, Plugin},
+    core::Name,
+    ecs::system::SystemState,
+    math::IVec2,
+    prelude::*,
+    utils::HashMap,
+    window::{
+        PrimaryWindow, WindowCloseRequested, WindowMode, WindowMoved, WindowPosition, WindowResized,
+    },
+};
+use winit::{event_loop::EventLoop, monitor::MonitorHandle};
+
+use crate::{BevyVulkanoWindows, WindowState};
+
+/// Key used for the primary window if it has no [`Name`]
+const PRIMARY_WINDOW_KEY: &str = "primary";
+
+/// Opt-in plugin that saves the position, size, mode and monitor of named windows to a file and
+/// restores them on the next run. Windows are identified by their [`Name`] component, the primary
+/// window is saved as `primary` if it has no name.
+///
+/// Must be added before [`VulkanoWinitPlugin`](crate::VulkanoWinitPlugin), which restores the
+/// geometry before creating the initial windows. Windows named later are restored when they get
+/// their [`Name`].
+pub struct WindowGeometryPlugin {
+    /// File the window geometry is saved to, e.g. in the application's config directory
+    pub path: PathBuf,
+}
+
+impl WindowGeometryPlugin {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        WindowGeometryPlugin {
+            path: path.into(),
+        }
+    }
+}
+
+impl Plugin for WindowGeometryPlugin {
+    fn build(&self, app: &mut App) {
+        if app
+            .world
+            .get_non_send_resource::<BevyVulkanoWindows>()
+            .is_some()
+        {
+            warn!(
+                "WindowGeometryPlugin was added after VulkanoWinitPlugin, initial window geometry \
+                 won't be restored"
+            );
+        }
+
+        app.insert_resource(PersistedWindowGeometry::load(self.path.clone()))
+            .add_system(restore_named_window_geometry_system.in_base_set(CoreSet::PreUpdate))
+            .add_systems(
+                (record_window_geometry_system, save_window_geometry_system)
+                    .chain()
+                    .in_base_set(CoreSet::Last),
+            );
+    }
+}
+
+/// Saved geometry of a window
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowGeometry {
+    /// Outer position in physical pixels
+    pub position: Option<IVec2>,
+    /// Logical inner size
+    pub size: Vec2,
+    pub mode: WindowMode,
+    /// Name of the monitor the window was on
+    pub monitor: Option<String>,
+}
+
+/// Window geometry by window name, loaded from and saved to the file of [`WindowGeometryPlugin`].
+#[derive(Resource)]
+pub struct PersistedWindowGeometry {
+    path: PathBuf,
+    windows: HashMap<String, WindowGeometry>,
+    dirty: bool,
+}
+
+impl PersistedWindowGeometry {
+    fn load(path: PathBuf) -> Self {
+        let windows = match fs::read_to_string(&path) {
+            Ok(contents) => contents.lines().filter_map(parse_line).collect(),
+            Err(_) => HashMap::default(),
+        };
+        PersistedWindowGeometry {
+            path,
+            windows,
+            dirty: false,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&WindowGeometry> {
+        self.windows.get(name)
+    }
+
+    fn save(&mut self) {
+        let contents = self
+            .windows
+            .iter()
+            .map(|(name, geometry)| format_line(name, geometry))
+            .collect::<Vec<_>>()
+            .join("\n");
+        match fs::write(&self.path, contents) {
+            Ok(_) => self.dirty = false,
+            Err(err) => error!("Failed to save window geometry to {:?}: {}", self.path, err),
+        }
+    }
+}
+
+// One window per line: x, y, width, height, mode, monitor and name separated by tabs. Missing
+// position and monitor are written as `-`. Name is last so it can contain anything but tabs and
+// newlines.
+fn format_line(name: &str, geometry: &WindowGeometry) -> String {
+    let (x, y) = geometry
+        .position
+        .map_or(("-".to_string(), "-".to_string()), |p| {
+            (p.x.to_string(), p.y.to_string())
+        });
+    let mode = match geometry.mode {
+        WindowMode::Windowed => "windowed",
+        WindowMode::BorderlessFullscreen => "borderless_fullscreen",
+        WindowMode::SizedFullscreen => "sized_fullscreen",
+        WindowMode::Fullscreen => "fullscreen",
+    };
+    format!(
+        "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+        x,
+        y,
+        geometry.size.x,
+        geometry.size.y,
+        mode,
+        geometry.monitor.as_deref().unwrap_or("-"),
+        name
+    )
+}
+
+fn parse_line(line: &str) -> Option<(String, WindowGeometry)> {
+    let mut fields = line.splitn(7, '\t');
+    let x = fields.next()?;
+    let y = fields.next()?;
+    let position = match (x.parse(), y.parse()) {
+        (Ok(x), Ok(y)) => Some(IVec2::new(x, y)),
+        _ => None,
+    };
+    let size = Vec2::new(fields.next()?.parse().ok()?, fields.next()?.parse().ok()?);
+    let mode = match fields.next()? {
+        "windowed" => WindowMode::Windowed,
+        "borderless_fullscreen" => WindowMode::BorderlessFullscreen,
+        "sized_fullscreen" => WindowMode::SizedFullscreen,
+        "fullscreen" => WindowMode::Fullscreen,
+        _ => return None,
+    };
+    let monitor = match fields.next()? {
+        "-" => None,
+        monitor => Some(monitor.to_string()),
+    };
+    let name = fields.next()?.to_string();
+    Some((name, WindowGeometry {
+        position,
+        size,
+        mode,
+        monitor,
+    }))
+}
+
+fn window_key(
+    entity: Entity,
+    name: Option<&Name>,
+    primary_window: Option<Entity>,
+) -> Option<String> {
+    match name {
+        Some(name) => Some(name.to_string()),
+        None if primary_window == Some(entity) => Some(PRIMARY_WINDOW_KEY.to_string()),
+        None => None,
+    }
+}
+
+/// Applies persisted geometry to windows that haven't been created yet. Called before the initial
+/// windows are created.
+pub(crate) fn restore_window_geometry<T>(world: &mut World, event_loop: &EventLoop<T>) {
+    let mut state: SystemState<(
+        Option<Res<PersistedWindowGeometry>>,
+        Query<(Entity, &mut Window, Option<&Name>)>,
+        Query<Entity, With<PrimaryWindow>>,
+    )> = SystemState::from_world(world);
+    let (persisted, mut windows, primary_window) = state.get_mut(world);
+
+    let persisted = if let Some(persisted) = persisted {
+        persisted
+    } else {
+        return;
+    };
+
+    for (entity, mut window, name) in windows.iter_mut() {
+        if let Some(geometry) = window_key(entity, name, primary_window.get_single().ok())
+            .and_then(|key| persisted.get(&key))
+        {
+            apply_window_geometry(
+                &mut window,
+                geometry,
+                event_loop.available_monitors(),
+                event_loop.primary_monitor(),
+            );
+        }
+    }
+}
+
+/// Restores the geometry of windows that were named after the initial windows were created. Size
+/// and mode are applied through [`Window`], the position is set on the winit window directly.
+fn restore_named_window_geometry_system(
+    persisted: Res<PersistedWindowGeometry>,
+    vulkano_windows: NonSend<BevyVulkanoWindows>,
+    mut windows: Query<(Entity, &mut Window, &Name), Added<Name>>,
+) {
+    for (entity, mut window, name) in windows.iter_mut() {
+        let geometry = if let Some(geometry) = persisted.get(name.as_str()) {
+            geometry
+        } else {
+            continue;
+        };
+        let winit_window = vulkano_windows.get_winit_window(entity);
+        // Monitors can be listed through any window
+        let any_winit_window = winit_window.or_else(|| {
+            vulkano_windows
+                .entity_to_winit
+                .keys()
+                .find_map(|entity| vulkano_windows.get_winit_window(*entity))
+        });
+        if let Some(any_winit_window) = any_winit_window {
+            apply_window_geometry(
+                &mut window,
+                geometry,
+                any_winit_window.available_monitors(),
+                any_winit_window.primary_monitor(),
+            );
+        }
+        if let (Some(winit_window), WindowPosition::At(position)) = (winit_window, window.position)
+        {
+            winit_window
+                .set_outer_position(winit::dpi::PhysicalPosition::new(position.x, position.y));
+        }
+    }
+}
+
+/// Sets the size, mode and position of the window. Positions are clamped so that windows stay on a
+/// currently available monitor.
+fn apply_window_geometry(
+    window: &mut Window,
+    geometry: &WindowGeometry,
+    mut available_monitors: impl Iterator<Item = MonitorHandle>,
+    primary_monitor: Option<MonitorHandle>,
+) {
+    window.resolution.set(geometry.size.x, geometry.size.y);
+    window.mode = geometry.mode;
+
+    // Prefer the monitor the window was on, fall back to primary monitor if it's gone
+    let monitor = available_monitors
+        .find(|monitor| monitor.name().is_some() && monitor.name() == geometry.monitor)
+        .or(primary_monitor);
+    if let (Some(position), Some(monitor)) = (geometry.position, monitor) {
+        let monitor_position = monitor.position();
+        let monitor_size = monitor.size();
+        let window_size = winit::dpi::LogicalSize::new(geometry.size.x, geometry.size.y)
+            .to_physical::<i32>(monitor.scale_factor());
+        let max_x = monitor_position.x + (monitor_size.width as i32 - window_size.width).max(0);
+        let max_y = monitor_position.y + (monitor_size.height as i32 - window_size.height).max(0);
+        window.position = WindowPosition::At(IVec2::new(
+            position.x.clamp(monitor_position.x, max_x),
+            position.y.clamp(monitor_position.y, max_y),
+        ));
+    }
+}
+
+fn record_window_geometry_system(
+    mut persisted: ResMut<PersistedWindowGeometry>,
+    vulkano_windows: NonSend<BevyVulkanoWindows>,
+    windows: Query<(Entity, &Window, &WindowState, Option<&Name>)>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+    mut window_moved_events: EventReader<WindowMoved>,
+    mut window_resized_events: EventReader<WindowResized>,
+) {
+    let mut changed_windows = window_moved_events
+        .iter()
+        .map(|event| event.entity)
+        .collect::<Vec<_>>();
+    changed_windows.extend(window_resized_events.iter().map(|event| event.window));
+
+    for entity in changed_windows {
+        let (entity, window, state, name) = if let Ok(window) = windows.get(entity) {
+            window
+        } else {
+            continue;
+        };
+        // Minimized windows report a zero size, and on Windows are moved off screen
+        if state.minimized || window.width() == 0.0 || window.height() == 0.0 {
+            continue;
+        }
+        let key = if let Some(key) = window_key(entity, name, primary_window.get_single().ok()) {
+            key
+        } else {
+            continue;
+        };
+        let winit_window = vulkano_windows.get_winit_window(entity);
+        let geometry = WindowGeometry {
+            position: winit_window
+                .and_then(|winit_window| winit_window.outer_position().ok())
+                .map(|position| IVec2::new(position.x, position.y)),
+            size: Vec2::new(window.width(), window.height()),
+            mode: window.mode,
+            monitor: winit_window
+                .and_then(|winit_window| winit_window.current_monitor())
+                .and_then(|monitor| monitor.name()),
+        };
+        if persisted.windows.get(&key) != Some(&geometry) {
+            persisted.windows.insert(key, geometry);
+            persisted.dirty = true;
+        }
+    }
+}
+
+fn save_window_geometry_system(
+    mut persisted: ResMut<PersistedWindowGeometry>,
+    mut window_close_requested_events: EventReader<WindowCloseRequested>,
+    mut app_exit_events: EventReader<AppExit>,
+) {
+    let closing = window_close_requested_events.iter().count() > 0;
+    let exiting = app_exit_events.iter().count() > 0;
+    if persisted.dirty && (closing || exiting) {
+        persisted.save();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_round_trip() {
+        let geometry = WindowGeometry {
+            position: Some(IVec2::new(-1920, 40)),
+            size: Vec2::new(1280.0, 720.5),
+            mode: WindowMode::BorderlessFullscreen,
+            monitor: Some("DELL U2720Q".to_string()),
+        };
+        let line = format_line("Tool palette", &geometry);
+        assert_eq!(
+            parse_line(&line),
+            Some(("Tool palette".to_string(), geometry))
+        );
+    }
+
+    #[test]
+    fn line_round_trip_without_position_and_monitor() {
+        let geometry = WindowGeometry {
+            position: None,
+            size: Vec2::new(800.0, 600.0),
+            mode: WindowMode::Windowed,
+            monitor: None,
+        };
+        let line = format_line("primary", &geometry);
+        assert_eq!(line, "-\t-\t800\t600\twindowed\t-\tprimary");
+        assert_eq!(parse_line(&line), Some(("primary".to_string(), geometry)));
+    }
+
+    #[test]
+    fn name_may_contain_spaces_and_dashes() {
+        let (name, _) = parse_line("0\t0\t800\t600\tfullscreen\t-\tA - B").unwrap();
+        assert_eq!(name, "A - B");
+    }
+
+    #[test]
+    fn invalid_lines_are_skipped() {
+        assert_eq!(parse_line(""), None);
+        assert_eq!(parse_line("0\t0\t800\t600\tmaximized\t-\tprimary"), None);
+        assert_eq!(parse_line("0\t0\twide\t600\twindowed\t-\tprimary"), None);
+        assert_eq!(parse_line("0\t0\t800\t600\twindowed\t-"), None);
+    }
+}